@@ -0,0 +1,94 @@
+/// recite.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Benchmark for SceneFragment::recite on a generated crowd scene with many minor parts.
+/// Times the priority-queue merge used by recite against the linear scan over every player that
+/// it replaced, on the same play. Run with: cargo bench --bench recite
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
+use lab2::{Diagnostics, Play, Player};
+
+const PLAYERS: usize = 2000;
+const LINES_PER_PLAYER: usize = 20;
+const RUNS: usize = 5;
+
+/// Writes a one-scene play where every player speaks in turn, so each line is from a different part
+fn write_crowd_scene(dir: &Path) -> PathBuf {
+    let mut config = String::new();
+    for player in 0..PLAYERS {
+        let part_filename = format!("extra_{}.txt", player);
+        config.push_str(&format!("Extra{} {}\n", player, part_filename));
+
+        let part: String = (0..LINES_PER_PLAYER)
+            .map(|turn| format!("{} Line {} of extra {}.\n", turn * PLAYERS + player, turn, player))
+            .collect();
+        fs::write(dir.join(part_filename), part).unwrap();
+    }
+    fs::write(dir.join("config.txt"), config).unwrap();
+
+    let script = dir.join("script.txt");
+    fs::write(&script, "[scene] A Crowd Scene\nconfig.txt\n").unwrap();
+    script
+}
+
+/// The old merge: scan every player for the smallest next line number before each line
+fn linear_recite(players: &mut [Player], out: &mut dyn io::Write) -> io::Result<()> {
+    let mut current_speaker = String::new();
+    loop {
+        let mut next: Option<(usize, usize)> = None;
+        for (index, player) in players.iter().enumerate() {
+            if let Some(line_number) = player.next_line() {
+                if next.is_none_or(|(smallest, _)| line_number < smallest) {
+                    next = Some((line_number, index));
+                }
+            }
+        }
+        match next {
+            Some((_, index)) => players[index].speak(&mut current_speaker, out)?,
+            None => return Ok(()),
+        }
+    }
+}
+
+fn load_players(dir: &Path) -> Vec<Player> {
+    let mut diagnostics = Diagnostics::new();
+    (0..PLAYERS).map(|player| {
+        let mut part = Player::new(&format!("Extra{}", player));
+        part.prepare(&dir.join(format!("extra_{}.txt", player)), &mut diagnostics).unwrap();
+        part
+    }).collect()
+}
+
+/// Median of several timed runs, each on freshly loaded parts since reciting uses them up
+fn median<T>(mut load: impl FnMut() -> T, mut run: impl FnMut(&mut T)) -> Duration {
+    let mut times: Vec<Duration> = (0..RUNS).map(|_| {
+        let mut loaded = load();
+        let start = Instant::now();
+        run(&mut loaded);
+        start.elapsed()
+    }).collect();
+    times.sort();
+    times[RUNS / 2]
+}
+
+fn main() {
+    let dir = env::temp_dir().join(format!("lab2_bench_recite_{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let script = write_crowd_scene(&dir);
+
+    let heap = median(|| {
+        let mut play = Play::new();
+        play.prepare(&script).unwrap();
+        play
+    }, |play| play.recite_to(&mut io::sink()).unwrap());
+    let linear = median(|| load_players(&dir), |players| linear_recite(players, &mut io::sink()).unwrap());
+    fs::remove_dir_all(&dir).unwrap();
+
+    println!("recite {} players x {} lines (median of {} runs)", PLAYERS, LINES_PER_PLAYER, RUNS);
+    println!("    priority queue: {:>10.2?}", heap);
+    println!("    linear scan:    {:>10.2?}", linear);
+    println!("    speedup:        {:>9.1}x", linear.as_secs_f64() / heap.as_secs_f64());
+}
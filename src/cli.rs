@@ -0,0 +1,229 @@
+/// cli.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Command-line parsing for the lab2 binary. Understands subcommands with named
+/// flags as well as the original "<script_file_name> [whinge|strict]" form.
+use lab2::declarations::{MIN_ARGS, MAX_ARGS, CONFIG_FILE_INDEX, VERBOSE_FLAG_INDEX};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Recite,
+    Check,
+    Stats,
+    Export,
+    Split,
+    Pack,
+    Unpack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub command: Command,
+    pub script_filename: String,
+    pub output_filename: Option<String>,
+    pub format: Format,
+    /// How warnings are written, both by check and in whinge mode
+    pub diagnostic_format: Format,
+    pub whinge: bool,
+    pub strict: bool,
+    /// Recite with one thread per player
+    pub concurrent: bool,
+    /// Load each fragment only when it is recited
+    pub stream: bool,
+    /// 1-based scene numbers to keep; empty means the whole play
+    pub scenes: Vec<usize>,
+    /// Where split or unpack writes the script, config and part files, or pack writes the bundle
+    pub destination: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum Invocation {
+    Run(Options),
+    Help,
+    Version,
+}
+
+pub fn usage(program_name: &str) -> String {
+    format!("usage: {0} <script_file_name> [whinge|strict]\n       \
+             {0} <command> [options] <script_file_name>\n       \
+             {0} split <rendered_file_name> <output_directory>\n       \
+             {0} pack <script_file_name> <bundle_file_name>\n       \
+             {0} unpack <bundle_file_name> <output_directory>\n       \
+             {0} --help | --version", program_name)
+}
+
+pub fn help(program_name: &str) -> String {
+    format!("{}\n\n\
+             commands:\n    \
+             recite    perform the play (the default)\n    \
+             check     load the play and report every problem found, without reciting\n    \
+             stats     print scene, fragment, part and line counts\n    \
+             export    write the loaded play out in the chosen format\n    \
+             split     turn a rendered script back into script, config and part files\n    \
+             pack      write a play and every file it uses into a single bundle file\n    \
+             unpack    write the files held in a bundle back out under a directory\n\n\
+             options:\n    \
+             -o, --output <file>     write to <file> instead of standard output\n    \
+             -f, --format <format>   text or json (check, stats and export)\n        \
+             --diagnostics <format>  write warnings as text or as one json object per line\n    \
+             -s, --scene <n>         only use scene <n>; may be given more than once\n        \
+             --whinge            print warnings to standard error\n        \
+             --strict            fail if loading the play produces any warning\n        \
+             --concurrent        recite with one thread per player (same output)\n        \
+             --stream            load each fragment just before reciting it (recite only)\n    \
+             -h, --help              print this help\n    \
+             -V, --version           print the version",
+            usage(program_name))
+}
+
+fn parse_command(word: &str) -> Option<Command> {
+    match word {
+        "recite" => Some(Command::Recite),
+        "check" => Some(Command::Check),
+        "stats" => Some(Command::Stats),
+        "export" => Some(Command::Export),
+        "split" => Some(Command::Split),
+        "pack" => Some(Command::Pack),
+        "unpack" => Some(Command::Unpack),
+        _ => None,
+    }
+}
+
+/// The original invocation: program_name script [whinge|strict]
+fn parse_legacy(args: &[String]) -> Option<Options> {
+    if args.len() < MIN_ARGS || args.len() > MAX_ARGS {
+        return None;
+    }
+    if args[1..].iter().any(|arg| arg.starts_with('-')) || parse_command(&args[CONFIG_FILE_INDEX]).is_some() {
+        return None;
+    }
+
+    let flag = args.get(VERBOSE_FLAG_INDEX).map(String::as_str);
+    if !matches!(flag, None | Some("whinge") | Some("strict")) {
+        return None;
+    }
+
+    Some(Options {
+        command: Command::Recite,
+        script_filename: args[CONFIG_FILE_INDEX].clone(),
+        output_filename: None,
+        format: Format::Text,
+        diagnostic_format: Format::Text,
+        whinge: flag == Some("whinge"),
+        strict: flag == Some("strict"),
+        concurrent: false,
+        stream: false,
+        scenes: Vec::new(),
+        destination: None,
+    })
+}
+
+fn parse_format(word: &str) -> Result<Format, String> {
+    match word {
+        "text" => Ok(Format::Text),
+        "json" => Ok(Format::Json),
+        other => Err(format!("unknown format '{}'", other)),
+    }
+}
+
+fn flag_value<'a>(flag: &str, rest: &mut impl Iterator<Item = &'a String>) -> Result<&'a String, String> {
+    rest.next().ok_or_else(|| format!("{} needs a value", flag))
+}
+
+/// Parses the full argument list, including the program name
+pub fn parse_args(args: &[String]) -> Result<Invocation, String> {
+    if let Some(options) = parse_legacy(args) {
+        return Ok(Invocation::Run(options));
+    }
+
+    let mut command: Option<Command> = None;
+    let mut positionals: Vec<&String> = Vec::new();
+    let mut output_filename: Option<String> = None;
+    let mut format: Option<Format> = None;
+    let mut diagnostic_format: Option<Format> = None;
+    let mut whinge = false;
+    let mut strict = false;
+    let mut concurrent = false;
+    let mut stream = false;
+    let mut scenes: Vec<usize> = Vec::new();
+
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Invocation::Help),
+            "-V" | "--version" => return Ok(Invocation::Version),
+            "--whinge" => whinge = true,
+            "--strict" => strict = true,
+            "--concurrent" => concurrent = true,
+            "--stream" => stream = true,
+            "-o" | "--output" => output_filename = Some(flag_value(arg, &mut rest)?.clone()),
+            "-f" | "--format" => format = Some(parse_format(flag_value(arg, &mut rest)?)?),
+            "--diagnostics" => diagnostic_format = Some(parse_format(flag_value(arg, &mut rest)?)?),
+            "-s" | "--scene" => {
+                let value = flag_value(arg, &mut rest)?;
+                match value.parse::<usize>() {
+                    Ok(scene) if scene > 0 => scenes.push(scene),
+                    _ => return Err(format!("'{}' is not a scene number", value)),
+                }
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option '{}'", flag)),
+            word => {
+                if command.is_none() && positionals.is_empty() && parse_command(word).is_some() {
+                    command = parse_command(word);
+                } else {
+                    positionals.push(arg);
+                }
+            }
+        }
+    }
+
+    let command = command.unwrap_or(Command::Recite);
+    let (script_filename, destination) = match (command, positionals.as_slice()) {
+        (Command::Split | Command::Pack | Command::Unpack, [source, destination]) => {
+            ((*source).clone(), Some((*destination).clone()))
+        }
+        (Command::Split, [_]) => return Err("split needs an output directory".to_string()),
+        (Command::Unpack, [_]) => return Err("unpack needs an output directory".to_string()),
+        (Command::Pack, [_]) => return Err("pack needs a bundle file name".to_string()),
+        (Command::Split | Command::Pack | Command::Unpack, [_, _, extra, ..]) => {
+            return Err(format!("unexpected argument '{}'", extra))
+        }
+        (_, [script_filename]) => ((*script_filename).clone(), None),
+        (_, []) => return Err("no script file given".to_string()),
+        (_, _) => return Err(format!("unexpected argument '{}'", positionals[1])),
+    };
+
+    let format = format.unwrap_or(Format::Text);
+    if format == Format::Json && !matches!(command, Command::Check | Command::Stats | Command::Export) {
+        return Err("--format json is only supported by check, stats and export".to_string());
+    }
+    // Every other command needs the whole play loaded
+    if stream && command != Command::Recite {
+        return Err("--stream only applies to recite".to_string());
+    }
+    // check's output is its diagnostics, so its format doubles as the diagnostic format
+    let diagnostic_format = match (command, diagnostic_format) {
+        (_, Some(diagnostic_format)) => diagnostic_format,
+        (Command::Check, None) => format,
+        (_, None) => Format::Text,
+    };
+
+    Ok(Invocation::Run(Options {
+        command,
+        script_filename,
+        output_filename,
+        format,
+        diagnostic_format,
+        whinge,
+        strict,
+        concurrent,
+        stream,
+        scenes,
+        destination,
+    }))
+}
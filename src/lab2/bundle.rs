@@ -0,0 +1,227 @@
+/// bundle.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: A single-file form of a play. A bundle is a text file holding the script and every
+/// include, config and part file it names, each in a section headed by its path relative to the
+/// script. Play::prepare loads a bundle directly: files named inside it are looked up in the bundle
+/// as though the bundle file were the directory holding the script. pack() and unpack() convert
+/// between a script tree and a bundle.
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use super::play::Play;
+use super::play_error::PlayError;
+use super::script_gen::{grab_trimmed_file_lines, grab_trimmed_lines, normalize, FileLines};
+
+// The first line of every bundle
+pub const BUNDLE_HEADER: &str = "%lab2-bundle 1";
+// Starts the section holding the file whose path follows it
+pub const FILE_MARKER: &str = "%file ";
+// Bundle lines starting with this are directives; a file line starting with it is written with it doubled
+pub const DIRECTIVE_MARKER: char = '%';
+
+pub type BundleFiles = Vec<(PathBuf, String)>; // (path_in_bundle, contents)
+
+pub struct Bundle {
+    path: PathBuf,
+    files: BundleFiles,
+}
+
+impl Bundle {
+    /// Creates an empty bundle that will be found at `path`
+    pub fn new<P: AsRef<Path>>(path: P) -> Bundle {
+        Bundle {
+            path: path.as_ref().to_path_buf(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Whether `path` is a readable file starting with the bundle header
+    pub fn is_bundle(path: &Path) -> bool {
+        let Ok(file) = File::open(path) else {
+            return false;
+        };
+        let mut first_line = String::new();
+        BufReader::new(file).read_line(&mut first_line).is_ok() && first_line.trim() == BUNDLE_HEADER
+    }
+
+    /// Reads the bundle file at `path`:
+    /// - Every line after the header belongs to the section of the last %file line above it
+    /// - Fails on lines outside any section, unknown directives, and file paths that are absolute,
+    ///   leave the bundle through "..", or appear twice
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Bundle, PlayError> {
+        let path = path.as_ref();
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(source) => return Err(PlayError::FailedToOpenFile { path: path.to_path_buf(), source }),
+        };
+        let invalid = |line: usize, message: String| PlayError::InvalidBundle { path: path.to_path_buf(), line, message };
+
+        let mut bundle = Bundle::new(path);
+        let mut lines = text.lines().enumerate().map(|(index, line)| (index + 1, line));
+        if lines.next().map(|(_, line)| line.trim()) != Some(BUNDLE_HEADER) {
+            return Err(invalid(1, format!("the first line is not '{}'", BUNDLE_HEADER)));
+        }
+
+        for (source_line, line) in lines {
+            if let Some(name) = line.strip_prefix(FILE_MARKER) {
+                let name = Path::new(name.trim());
+                let member = normalize(name);
+                if name.as_os_str().is_empty() || !Self::stays_inside(&member) {
+                    return Err(invalid(source_line, format!("'{}' is not a path inside the bundle", name.display())));
+                }
+                if bundle.contents(&member).is_some() {
+                    return Err(invalid(source_line, format!("'{}' appears more than once", member.display())));
+                }
+                bundle.files.push((member, String::new()));
+            } else if line.starts_with(DIRECTIVE_MARKER) && !line[1..].starts_with(DIRECTIVE_MARKER) {
+                return Err(invalid(source_line, format!("unknown bundle directive '{}'", line)));
+            } else {
+                match bundle.files.last_mut() {
+                    Some((_, contents)) => {
+                        contents.push_str(line.strip_prefix(DIRECTIVE_MARKER).unwrap_or(line));
+                        contents.push('\n');
+                    }
+                    None => return Err(invalid(source_line, "this line is not in any %file section".to_string())),
+                }
+            }
+        }
+
+        if bundle.files.is_empty() {
+            return Err(invalid(1, "the bundle holds no files".to_string()));
+        }
+        Ok(bundle)
+    }
+
+    /// Whether a normalized relative path stays inside the directory it is relative to
+    fn stays_inside(member: &Path) -> bool {
+        member.components().all(|component| matches!(component, Component::Normal(_)))
+    }
+
+    /// Adds a file to the bundle; the first file added is the script
+    pub fn add(&mut self, path_in_bundle: &Path, contents: String) {
+        self.files.push((normalize(path_in_bundle), contents));
+    }
+
+    /// The path the bundle was loaded from or will be written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The files of the bundle, the script first
+    pub fn files(&self) -> &BundleFiles {
+        &self.files
+    }
+
+    /// Path of the bundled script, inside the bundle
+    pub fn script_path(&self) -> PathBuf {
+        self.path.join(self.files.first().map_or(Path::new(""), |(member, _)| member.as_path()))
+    }
+
+    fn contents(&self, member: &Path) -> Option<&str> {
+        self.files.iter().find(|(name, _)| name == member).map(|(_, contents)| contents.as_str())
+    }
+
+    /// Reads the lines of a file inside the bundle, named as bundle_path/path_in_bundle, the way
+    /// grab_trimmed_file_lines() reads a file; files outside the bundle or missing from it cannot be opened
+    pub fn grab_trimmed_lines(&self, filename: &Path, lines: &mut FileLines) -> Result<(), PlayError> {
+        let member = filename.strip_prefix(&self.path).ok().map(normalize).filter(|member| Self::stays_inside(member));
+        let contents = match member.as_deref().and_then(|member| self.contents(member)) {
+            Some(contents) => contents,
+            None => {
+                let reason = match member {
+                    Some(_) => format!("no such file in bundle '{}'", self.path.display()),
+                    None => format!("not inside bundle '{}'", self.path.display()),
+                };
+                return Err(PlayError::FailedToOpenFile {
+                    path: filename.to_path_buf(),
+                    source: io::Error::new(io::ErrorKind::NotFound, reason),
+                });
+            }
+        };
+        grab_trimmed_lines(contents.as_bytes(), filename, lines)
+    }
+
+    /// Writes the bundle in its text form
+    pub fn write_to(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "{}", BUNDLE_HEADER)?;
+        for (member, contents) in &self.files {
+            writeln!(out, "{}{}", FILE_MARKER, member.display())?;
+            for line in contents.lines() {
+                if line.starts_with(DIRECTIVE_MARKER) {
+                    write!(out, "{}", DIRECTIVE_MARKER)?;
+                }
+                writeln!(out, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the lines of `filename` from `bundle` if the play is bundled, otherwise from the file system
+pub fn grab_lines(bundle: Option<&Bundle>, filename: &Path, lines: &mut FileLines) -> Result<(), PlayError> {
+    match bundle {
+        Some(bundle) => bundle.grab_trimmed_lines(filename, lines),
+        None => grab_trimmed_file_lines(filename, lines),
+    }
+}
+
+fn read_file(path: &Path) -> Result<String, PlayError> {
+    fs::read_to_string(path).map_err(|source| PlayError::FailedToOpenFile { path: path.to_path_buf(), source })
+}
+
+fn write_file(path: &Path, contents: &[u8]) -> Result<(), PlayError> {
+    if let Some(directory) = path.parent() {
+        if let Err(source) = fs::create_dir_all(directory) {
+            return Err(PlayError::FailedToOpenFile { path: directory.to_path_buf(), source });
+        }
+    }
+    fs::write(path, contents).map_err(|source| PlayError::FailedToOpenFile { path: path.to_path_buf(), source })
+}
+
+/// Packs the play whose script is `script_filename` into a bundle written to `bundle_filename`:
+/// - Loads the play to find every included script, config file and part file it uses
+/// - Each file is stored under its path relative to the script's directory; fails if one lies outside it
+/// - Fails unless the bundle loads again without reading anything outside it
+pub fn pack<P: AsRef<Path>, Q: AsRef<Path>>(script_filename: P, bundle_filename: Q) -> Result<(), PlayError> {
+    let script_path = script_filename.as_ref();
+    let bundle_path = bundle_filename.as_ref();
+
+    let mut play = Play::new();
+    play.prepare(script_path)?;
+
+    let directory = script_path.parent().unwrap_or(Path::new(""));
+    let configs = play.fragments().iter().map(|fragment| fragment.config_filename().to_path_buf());
+    let parts = play.fragments().iter()
+        .flat_map(|fragment| fragment.players().iter().map(|player| player.part_filename().to_path_buf()));
+    let mut bundle = Bundle::new(bundle_path);
+    for file in play.script_files().iter().cloned().chain(configs).chain(parts) {
+        let member = file.strip_prefix(directory).ok().map(normalize).filter(|member| Bundle::stays_inside(member));
+        let Some(member) = member else {
+            return Err(PlayError::UnbundlableFile { path: file.clone(), directory: directory.to_path_buf() });
+        };
+        // Inline configs name their script, and parts may be shared between fragments
+        if bundle.contents(&member).is_none() {
+            bundle.add(&member, read_file(&file)?);
+        }
+    }
+
+    // Absolute paths inside the files would still be read from outside the bundle
+    let bundle = Arc::new(bundle);
+    Play::new().prepare_bundle(Arc::clone(&bundle))?;
+
+    let mut text: Vec<u8> = Vec::new();
+    bundle.write_to(&mut text).map_err(|source| PlayError::FailedToWriteOutput { source })?;
+    write_file(bundle_path, &text)
+}
+
+/// Writes the files of the bundle `bundle_filename` under `directory` (created if needed), and
+/// returns the path of the unpacked script file
+pub fn unpack<P: AsRef<Path>, Q: AsRef<Path>>(bundle_filename: P, directory: Q) -> Result<PathBuf, PlayError> {
+    let bundle = Bundle::load(bundle_filename)?;
+    let directory = directory.as_ref();
+    for (member, contents) in bundle.files() {
+        write_file(&directory.join(member), contents.as_bytes())?;
+    }
+    Ok(directory.join(&bundle.files()[0].0))
+}
@@ -0,0 +1,258 @@
+/// diagnostics.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Collects the warnings raised while loading a play. Every Play owns its own
+/// Diagnostics, so plays loaded side by side do not interfere, and the caller decides
+/// whether to print, count, filter or discard what was found. In keep-going mode files
+/// that cannot be read are recorded here as errors instead of stopping the load.
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use super::json::{optional_number, quote};
+use super::play_error::{included_from, PlayError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// The kinds of problems that are tolerated while loading a play
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticKind {
+    /// A [scene] line in the script has no title after it
+    SceneWithoutTitle,
+    /// A config filename, [include], [config] or [end] line in the script has extra tokens after it
+    ExtraScriptTokens,
+    /// An [include] line in the script does not name a script to include
+    IncludeWithoutFile,
+    /// A [config] block in the script runs to the end of its file without an [end] line
+    ConfigWithoutEnd,
+    /// An [end] line in the script does not close a [config] block
+    EndWithoutConfig,
+    /// A script or config line has a quote that is never closed
+    UnterminatedQuote,
+    /// A scene config line has fewer than two tokens
+    TooFewConfigTokens,
+    /// A scene config line has more than two tokens
+    TooManyConfigTokens,
+    /// A scene config line has a key=value option that is not a valid enter= or exit= cue
+    InvalidConfigOption,
+    /// A part file line does not start with a valid line number
+    InvalidLineNumber,
+    /// A part file continuation line has no numbered line before it to continue
+    StrayContinuationLine,
+    /// A part file continuation line has no text after its marker
+    EmptyContinuationLine,
+    /// A line number is skipped within a scene fragment
+    MissingLineNumber,
+    /// A line number is used more than once within a scene fragment
+    DuplicateLineNumber,
+    /// A config or part file could not be opened or read (keep-going mode only)
+    UnreadableFile,
+    /// A config file has no lines (keep-going mode only)
+    EmptyFile,
+}
+
+impl DiagnosticKind {
+    /// Stable identifier for the kind, used in machine-readable output
+    pub fn code(&self) -> &'static str {
+        match self {
+            DiagnosticKind::SceneWithoutTitle => "scene-without-title",
+            DiagnosticKind::ExtraScriptTokens => "extra-script-tokens",
+            DiagnosticKind::IncludeWithoutFile => "include-without-file",
+            DiagnosticKind::ConfigWithoutEnd => "config-without-end",
+            DiagnosticKind::EndWithoutConfig => "end-without-config",
+            DiagnosticKind::UnterminatedQuote => "unterminated-quote",
+            DiagnosticKind::TooFewConfigTokens => "too-few-config-tokens",
+            DiagnosticKind::TooManyConfigTokens => "too-many-config-tokens",
+            DiagnosticKind::InvalidConfigOption => "invalid-config-option",
+            DiagnosticKind::InvalidLineNumber => "invalid-line-number",
+            DiagnosticKind::StrayContinuationLine => "stray-continuation-line",
+            DiagnosticKind::EmptyContinuationLine => "empty-continuation-line",
+            DiagnosticKind::MissingLineNumber => "missing-line-number",
+            DiagnosticKind::DuplicateLineNumber => "duplicate-line-number",
+            DiagnosticKind::UnreadableFile => "unreadable-file",
+            DiagnosticKind::EmptyFile => "empty-file",
+        }
+    }
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+    /// The file the problem was found in
+    pub file: PathBuf,
+    /// The 1-based line within `file`, when the problem belongs to a single line
+    pub line: Option<usize>,
+    /// The 1-based column (in characters) of the offending token, when there is one
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// "file:line", or just "file" when the problem is not tied to a single line
+    pub fn location(&self) -> String {
+        match self.line {
+            Some(line) => format!("{}:{}", self.file.display(), line),
+            None => self.file.display().to_string(),
+        }
+    }
+
+    /// A single-line JSON object with the severity, code, file, line, column and message
+    pub fn to_json(&self) -> String {
+        format!("{{\"severity\":{},\"code\":{},\"file\":{},\"line\":{},\"column\":{},\"message\":{}}}",
+                quote(self.severity.name()), quote(self.kind.code()), quote(&self.file.display().to_string()),
+                optional_number(self.line), optional_number(self.column), quote(&self.message))
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Warning => write!(f, "Warning: {}", self.message),
+            Severity::Error => write!(f, "Error: {}", self.message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    entries: Vec<Diagnostic>,
+    keep_going: bool,
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics { entries: Vec::new(), keep_going: false }
+    }
+
+    /// In keep-going mode unreadable or empty config and part files are recorded
+    /// by tolerate() and skipped, so that one load can report all of them
+    pub fn set_keep_going(&mut self, keep_going: bool) {
+        self.keep_going = keep_going;
+    }
+
+    /// An empty collector with the same settings, for loading a file on another thread
+    pub(crate) fn fork(&self) -> Diagnostics {
+        Diagnostics { entries: Vec::new(), keep_going: self.keep_going }
+    }
+
+    /// Removes the diagnostics recorded after the first `count` and returns them in a fork
+    pub(crate) fn split_off(&mut self, count: usize) -> Diagnostics {
+        Diagnostics { entries: self.entries.split_off(count), keep_going: self.keep_going }
+    }
+
+    /// Appends everything a fork collected, after the diagnostics already recorded
+    pub(crate) fn merge(&mut self, other: Diagnostics) {
+        self.entries.extend(other.entries);
+    }
+
+    /// Records a warning against a file and, optionally, a line and column within it
+    pub fn warn(&mut self, kind: DiagnosticKind, file: &Path, line: Option<usize>, column: Option<usize>,
+                message: String) {
+        self.push(Severity::Warning, kind, file, line, column, message);
+    }
+
+    fn push(&mut self, severity: Severity, kind: DiagnosticKind, file: &Path, line: Option<usize>,
+            column: Option<usize>, message: String) {
+        self.entries.push(Diagnostic {
+            severity,
+            kind,
+            file: file.to_path_buf(),
+            line,
+            column,
+            message,
+        });
+    }
+
+    /// Decides what happens to an error from loading a file named at `file:line`:
+    /// in keep-going mode file errors are recorded and Ok is returned so the caller
+    /// can skip that file; any other error is handed back
+    /// - A file reached through [include] lines is recorded with those lines, as warnings in included scripts are
+    pub fn tolerate(&mut self, error: PlayError, file: &Path, line: usize) -> Result<(), PlayError> {
+        let kind = match error.unwrapped() {
+            PlayError::FailedToOpenFile { .. } | PlayError::FailedToReadLine { .. } => DiagnosticKind::UnreadableFile,
+            PlayError::EmptyConfig { .. } | PlayError::EmptyInlineConfig { .. } => DiagnosticKind::EmptyFile,
+            _ => return Err(error),
+        };
+        if !self.keep_going {
+            return Err(error);
+        }
+        let message = match &error {
+            PlayError::Included { error, chain } => error.to_string() + &included_from(&chain[1..]),
+            _ => error.to_string(),
+        };
+        self.push(Severity::Error, kind, file, Some(line), None, message);
+        Ok(())
+    }
+
+    /// Number of diagnostics that are errors rather than warnings
+    pub fn error_count(&self) -> usize {
+        self.entries.iter().filter(|diagnostic| diagnostic.severity == Severity::Error).count()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of diagnostics of the given kind
+    pub fn count(&self, kind: DiagnosticKind) -> usize {
+        self.of_kind(kind).count()
+    }
+
+    /// Iterates over the diagnostics of the given kind only
+    pub fn of_kind(&self, kind: DiagnosticKind) -> impl Iterator<Item = &Diagnostic> {
+        self.entries.iter().filter(move |diagnostic| diagnostic.kind == kind)
+    }
+
+    /// Writes one "Warning: ..." line per diagnostic, in the order they were found
+    pub fn report(&self, out: &mut dyn Write) -> io::Result<()> {
+        for diagnostic in &self.entries {
+            writeln!(out, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+
+    /// Writes one JSON object per line (JSON Lines) per diagnostic, in the order they were found
+    pub fn report_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        for diagnostic in &self.entries {
+            writeln!(out, "{}", diagnostic.to_json())?;
+        }
+        Ok(())
+    }
+
+    /// Writes one "file:line: message" line per diagnostic, in the order they were found
+    pub fn report_locations(&self, out: &mut dyn Write) -> io::Result<()> {
+        for diagnostic in &self.entries {
+            writeln!(out, "{}: {}", diagnostic.location(), diagnostic.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
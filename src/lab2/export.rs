@@ -0,0 +1,25 @@
+/// export.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Writes a loaded play out as JSON: its fragments in order, each with its
+/// title, scene number, config file and the name, identity and numbered lines of every player.
+use std::io::{self, Write};
+use super::json::quote;
+use super::play::Play;
+
+pub fn write_json(play: &Play, out: &mut dyn Write) -> io::Result<()> {
+    let mut fragments: Vec<String> = Vec::new();
+    for fragment in play.fragments() {
+        let mut players: Vec<String> = Vec::new();
+        for player in fragment.players() {
+            let lines: Vec<String> = player.lines().iter()
+                .map(|(line_number, text, _)| format!("{{\"number\":{},\"text\":{}}}", line_number, quote(text)))
+                .collect();
+            players.push(format!("{{\"name\":{},\"identity\":{},\"lines\":[{}]}}",
+                                 quote(player.name()), quote(player.identity()), lines.join(",")));
+        }
+        fragments.push(format!("{{\"scene\":{},\"title\":{},\"config\":{},\"players\":[{}]}}",
+                               fragment.scene(), quote(fragment.title()), quote(&fragment.config_filename().display().to_string()),
+                               players.join(",")));
+    }
+    writeln!(out, "{{\"fragments\":[{}]}}", fragments.join(","))
+}
@@ -0,0 +1,26 @@
+/// json.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: The small amount of JSON support needed for machine-readable output.
+/// Quotes and escapes a string as a JSON string literal
+pub fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// A number as JSON, or null when there is none
+pub fn optional_number(value: Option<usize>) -> String {
+    value.map_or("null".to_string(), |n| n.to_string())
+}
@@ -1,9 +1,19 @@
 /// mod.rs
 /// Author: Zichu Pan, Edgar Palomino
 /// Summary: module declaration
+pub mod bundle;
 pub mod declarations;
+pub mod diagnostics;
+pub mod export;
+pub mod json;
+pub mod parallel;
 pub mod script_gen;
 pub mod play;
 pub mod player;
+pub mod play_error;
 pub mod return_wrapper;
-pub mod scene_fragment;
\ No newline at end of file
+pub mod scene_fragment;
+pub mod sink;
+pub mod split;
+pub mod stats;
+pub mod tokenizer;
\ No newline at end of file
@@ -0,0 +1,43 @@
+/// parallel.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Runs independent pieces of work, such as loading the files of a play, on scoped
+/// threads. Results always come back in the order of the inputs, so callers can report what
+/// happened in file order no matter which thread finished first.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// Loading files mostly waits on I/O, so it is worth running more threads than there are CPUs
+const THREADS_PER_CPU: usize = 2;
+
+/// Applies `work` to every item on a small pool of scoped threads and returns the results in item order:
+/// - Each thread repeatedly claims the next unclaimed item, so slow items do not hold up the others
+/// - A single item (or none) is handled on the calling thread
+pub fn map_in_order<T: Sync, R: Send>(items: &[T], work: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let cpus = thread::available_parallelism().map_or(1, |cpus| cpus.get());
+    let threads = items.len().min(cpus * THREADS_PER_CPU);
+    if threads <= 1 {
+        return items.iter().map(work).collect();
+    }
+
+    let next_item = AtomicUsize::new(0);
+    let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads).map(|_| scope.spawn(|| {
+            let mut done: Vec<(usize, R)> = Vec::new();
+            loop {
+                let index = next_item.fetch_add(1, Ordering::Relaxed);
+                match items.get(index) {
+                    Some(item) => done.push((index, work(item))),
+                    None => return done,
+                }
+            }
+        })).collect();
+
+        // A panic in a worker is passed on to the caller as it would be without threads
+        handles.into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+            .collect()
+    });
+
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
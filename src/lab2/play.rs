@@ -1,109 +1,501 @@
 /// play.rs
 /// Author: Zichu Pan, Edgar Palomino
 /// Summary: This module implements the core Play structure that orchestrates a performance by managing scene fragments.
-use std::sync::atomic::Ordering;
-use super::scene_fragment::SceneFragment;
-use super::declarations::{WHINGE_MODE, SCRIPT_PARSING_ERROR};
-use super::script_gen::grab_trimmed_file_lines;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use super::bundle::{grab_lines, Bundle};
+use super::scene_fragment::{Cast, PartConfig, PlayConfig, PreparedPlayer, SceneFragment};
+use super::diagnostics::{Diagnostics, DiagnosticKind, Severity};
+use super::parallel::map_in_order;
+use super::play_error::{included_from, PlayError};
+use super::script_gen::{column_of, normalize, resolve_relative, FileLines};
+use super::tokenizer::{tokenize, Tokens};
 
-pub type ScriptConfig = Vec<(bool, String)>;
+pub type Includes = Vec<(PathBuf, usize)>; // (including_script, source_line) of each [include], outermost first
+pub type ScriptConfig = Vec<ScriptEntry>;
 pub type Fragments = Vec<SceneFragment>;
 
 const CONFIG_FILENAME_INDEX: usize = 0;
 const CONFIG_SCRIPT_LENGTH: usize = 1;
 const SCENE_SCRIPT_INDEX: usize = 0;
 const SCENE_SCRIPT_LENGTH: usize = 2;
+const SCENE_DIRECTIVE: &str = "[scene]";
+const INCLUDE_DIRECTIVE: &str = "[include]";
+const INCLUDE_SCRIPT_LENGTH: usize = 2;
+const CONFIG_DIRECTIVE: &str = "[config]";
+const END_DIRECTIVE: &str = "[end]";
+const BLOCK_DIRECTIVE_LENGTH: usize = 1;
+
+/// What a script line adds to the play
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptEntryKind {
+    /// A [scene] line; the text is the title
+    SceneTitle,
+    /// A config filename, or a [config] block whose lines are held in the entry
+    Config,
+}
+
+/// A script line that adds a scene title or a fragment to the play, with where it was read
+#[derive(Debug, Clone)]
+pub struct ScriptEntry {
+    kind: ScriptEntryKind,
+    // The scene title, or the config filename as written (empty for a [config] block)
+    text: String,
+    source_line: usize,
+    script_path: PathBuf,
+    // The lines of a [config] block, as written
+    inline_config: Option<FileLines>,
+    // The [include] lines the script was read through
+    includes: Includes,
+    // How many diagnostics were collected before the line, so that those found loading the
+    // fragment can be reported in among them in script order
+    diagnostics_before: usize,
+}
+
+impl ScriptEntry {
+    pub fn kind(&self) -> ScriptEntryKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The (1-based) line of script_path() the entry was read from
+    pub fn source_line(&self) -> usize {
+        self.source_line
+    }
+
+    pub fn script_path(&self) -> &Path {
+        &self.script_path
+    }
+}
+
+/// A fragment to be created from a config entry of the script
+struct FragmentPlan<'a> {
+    title: String,
+    scene: usize,
+    config_path: PathBuf,
+    entry: &'a ScriptEntry,
+}
+
+/// A fragment whose config has been read, with what reading it found
+struct ReadFragment {
+    fragment: SceneFragment,
+    config: PlayConfig,
+    diagnostics: Diagnostics,
+    result: Result<(), PlayError>,
+}
 
 pub struct Play {
+    script_filename: PathBuf,
+    // Every script file read, the top-level script first and then each included script
+    script_files: Vec<PathBuf>,
+    // The bundle the play is read from, if it was given as one
+    bundle: Option<Arc<Bundle>>,
+    // The [include] lines being read
+    includes: Includes,
+    // (source_line, first_column) of the [config] line whose block is being read, if any
+    open_config_block: Option<(usize, usize)>,
     fragments: Fragments,
+    diagnostics: Diagnostics,
+    strict: bool,
+    concurrent: bool,
+    streaming: bool,
+}
+
+impl Default for Play {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Play {
     pub fn new() -> Play {
         Play {
+            script_filename: PathBuf::new(),
+            script_files: Vec::new(),
+            bundle: None,
+            includes: Vec::new(),
+            open_config_block: None,
             fragments: Vec::new(),
+            diagnostics: Diagnostics::new(),
+            strict: false,
+            concurrent: false,
+            streaming: false,
         }
     }
 
+    /// In strict mode any warning collected while loading makes prepare() fail
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// In concurrent mode each fragment is recited with one thread per player
+    /// (SceneFragment::recite_concurrently); the performance is the same either way
+    pub fn set_concurrent(&mut self, concurrent: bool) {
+        self.concurrent = concurrent;
+    }
+
+    /// In streaming mode prepare() only reads the script, and each fragment's config and part files
+    /// are loaded just before it is recited and dropped afterwards, so only one fragment is in memory
+    /// at a time. Warnings about those files are collected as they are loaded.
+    pub fn set_streaming(&mut self, streaming: bool) {
+        self.streaming = streaming;
+    }
+
+    /// In keep-going mode config and part files that cannot be read are recorded as
+    /// error diagnostics and skipped, so a single load reports every problem
+    pub fn set_keep_going(&mut self, keep_going: bool) {
+        self.diagnostics.set_keep_going(keep_going);
+    }
+
+    /// Warnings collected while this play was loaded
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    /// The script files read while loading the play: the script itself, then any included scripts
+    pub fn script_files(&self) -> &[PathBuf] {
+        &self.script_files
+    }
+
+    /// The fragments of the play, in recitation order
+    pub fn fragments(&self) -> &Fragments {
+        &self.fragments
+    }
+
+    /// Number of scenes, i.e. titled [scene] lines that were followed by a fragment
+    pub fn scene_count(&self) -> usize {
+        let mut scenes: Vec<usize> = self.fragments.iter().map(|fragment| fragment.scene()).collect();
+        scenes.dedup();
+        scenes.len()
+    }
+
+    /// Keeps only the fragments of the given (1-based) scenes, so that reciting
+    /// performs just those scenes with their own entrances and exits
+    pub fn select_scenes(&mut self, scenes: &[usize]) -> Result<(), PlayError> {
+        let scene_count = self.scene_count();
+        if let Some(&scene) = scenes.iter().find(|&&scene| !self.fragments.iter().any(|f| f.scene() == scene)) {
+            return Err(PlayError::NoSuchScene { scene, scene_count });
+        }
+        self.fragments.retain(|fragment| scenes.contains(&fragment.scene()));
+        Ok(())
+    }
+
     /// Converts the ScriptConfig into SceneFragment objects:
-    /// - Scene titles are stored temporarily
-    /// - Config filenames trigger creation of new fragments with the current title
-    pub fn process_config(&mut self, config: &ScriptConfig) -> Result<(), u8> {
+    /// - Scene titles are stored temporarily; the next fragment takes the title and starts a new scene,
+    ///   so a title with no fragment after it is replaced by the next one and is not numbered
+    /// - Config filenames trigger creation of new fragments with the current title, and are
+    ///   resolved relative to the directory of the script file that names them
+    /// - Inline [config] blocks create fragments the same way, with the script file standing in
+    ///   for the config file
+    /// - In streaming mode the fragments are created but not prepared
+    /// - The fragments' configs are read concurrently, and then the parts of all fragments on one
+    ///   pool of threads; the players and fragments are added and reported in script order, stopping
+    ///   at the first error that is not tolerated just as preparing them one at a time would
+    pub fn process_config(&mut self, config: &ScriptConfig) -> Result<(), PlayError> {
         let mut title = String::new();
-        
-        for config_entry in config {
-            match config_entry {
-                (is_scene_title, text) => {
-                    if *is_scene_title {
-                        // Update the title string
-                        title = text.clone();
-                    } else {
-                        let mut fragment = SceneFragment::new(&title);
-                        title = String::new();
-                        if let Err(error_code) = fragment.prepare(text) {
-                            return Err(error_code);
-                        }
-                        // Add the fragment to the play
-                        self.fragments.push(fragment);
+        let mut scene: usize = 0;
+        // Each fragment's config, in script order
+        let mut plans: Vec<FragmentPlan> = Vec::new();
+
+        for entry in config {
+            match entry.kind {
+                ScriptEntryKind::SceneTitle => {
+                    // Update the title string
+                    title = entry.text.clone();
+                }
+                ScriptEntryKind::Config => {
+                    if !title.is_empty() {
+                        scene += 1;
                     }
+                    let config_path = match entry.inline_config {
+                        Some(_) => entry.script_path.clone(),
+                        None => resolve_relative(&entry.script_path, &entry.text),
+                    };
+                    plans.push(FragmentPlan { title, scene, config_path, entry });
+                    title = String::new();
                 }
             }
         }
+
+        let plan = |plan: &FragmentPlan| {
+            let entry = plan.entry;
+            let mut fragment = SceneFragment::new(&plan.title, plan.scene);
+            fragment.set_config_filename(&plan.config_path);
+            fragment.set_bundle(self.bundle.clone());
+            if let Some(lines) = &entry.inline_config {
+                fragment.set_inline_config(entry.source_line, lines.clone());
+            }
+            if !entry.includes.is_empty() {
+                fragment.set_included_from(include_chain(&entry.script_path, entry.source_line, &entry.includes));
+            }
+            fragment
+        };
+
+        if self.streaming {
+            // Only plan the fragments; stream() loads each one when its turn comes
+            let planned: Vec<SceneFragment> = plans.iter().map(plan).collect();
+            self.fragments.extend(planned);
+            return Ok(());
+        }
+
+        // Parts are read on a single pool rather than one pool per fragment, so that fragments
+        // with few parts do not leave threads idle and no threads are started from other threads
+        let read = map_in_order(&plans, |fragment_plan| {
+            let mut fragment = plan(fragment_plan);
+            let mut config: PlayConfig = Vec::new();
+            let mut diagnostics = self.diagnostics.fork();
+            let result = fragment.read_config(&fragment_plan.config_path, &mut config, &mut diagnostics);
+            ReadFragment { fragment, config, diagnostics, result }
+        });
+        let parts: Vec<(&SceneFragment, &PartConfig)> = read.iter()
+            .filter(|read_fragment| read_fragment.result.is_ok())
+            .flat_map(|read_fragment| read_fragment.config.iter().map(|part_config| (&read_fragment.fragment, part_config)))
+            .collect();
+        let mut prepared = map_in_order(&parts, |(fragment, part_config)| {
+            fragment.prepare_player(part_config, &self.diagnostics)
+        }).into_iter();
+
+        // The diagnostics found while reading the script go back in among the fragments' in script
+        // order; `parsed` holds those not yet put back, `merged` counts those that were
+        let mut parsed = self.diagnostics.split_off(0);
+        let mut merged: usize = 0;
+        let mut result = Ok(());
+        for (read_fragment, plan) in read.into_iter().zip(&plans) {
+            let later = parsed.split_off(plan.entry.diagnostics_before.saturating_sub(merged).min(parsed.len()));
+            merged += parsed.len();
+            self.diagnostics.merge(std::mem::replace(&mut parsed, later));
+            result = self.add_fragment(read_fragment, &mut prepared, plan.entry);
+            if result.is_err() {
+                break;
+            }
+        }
+        self.diagnostics.merge(parsed);
+        result
+    }
+
+    /// Adds a fragment whose config was read for `entry`, taking its players from `players`, after
+    /// the diagnostics of each, as preparing the fragment on its own would
+    fn add_fragment(&mut self, ReadFragment { mut fragment, config, diagnostics, result }: ReadFragment,
+                    players: &mut impl Iterator<Item = PreparedPlayer>, entry: &ScriptEntry) -> Result<(), PlayError> {
+        self.diagnostics.merge(diagnostics);
+        if let Err(error) = result {
+            return self.diagnostics.tolerate(error, &entry.script_path, entry.source_line);
+        }
+        for ((_, _, part_line, _, _), player) in config.iter().zip(players) {
+            fragment.add_player(player, *part_line, &mut self.diagnostics)?;
+        }
+        fragment.sort_players(&mut self.diagnostics);
+        // Add the fragment to the play
+        self.fragments.push(fragment);
         Ok(())
     }
 
+    /// Where the script being read was included from, innermost [include] first, as
+    /// " (included from b.txt:2, a.txt:5)"; empty for the top-level script
+    fn include_context(&self) -> String {
+        let includes: Includes = self.includes.iter().rev().cloned().collect();
+        included_from(&includes)
+    }
+
+    /// Records a diagnostic against a script line, noting the [include] chain that led to it
+    fn warn_script(&mut self, kind: DiagnosticKind, script_path: &Path, (source_line, column): (usize, usize),
+                   message: String) {
+        let message = message + &self.include_context();
+        self.diagnostics.warn(kind, script_path, Some(source_line), Some(column), message);
+    }
+
     /// Processes individual lines:
     /// - Lines starting with [scene] are treated as scene titles
+    /// - Lines starting with [include] splice in the lines of another script, resolved relative to this one
+    /// - A [config] line starts a fragment whose config lines follow inline, up to an [end] line
+    ///   (or, with a diagnostic, up to the next [scene], [include] or [config] line)
     /// - Other non-blank lines are treated as configuration filenames
-    /// - Warns about missing scene titles or extra tokens (in whinge mode)
-    fn add_config(&mut self, line: &String, config: &mut ScriptConfig) {
+    /// - Records a diagnostic for missing scene titles, missing include names, stray [end] lines or extra tokens
+    fn add_config(&mut self, line: &str, script_path: &Path, (source_line, first_column): (usize, usize),
+                  config: &mut ScriptConfig) -> Result<(), PlayError> {
         // Ignore blank lines
         if line.trim().is_empty() {
-            return;
+            return Ok(());
         }
 
         let tokens: Vec<&str> = line.split_whitespace().collect();
-        
-        if tokens[SCENE_SCRIPT_INDEX] == "[scene]" {
+
+        // A directive cannot be a config line, so a block it appears in was meant to end before it
+        if [SCENE_DIRECTIVE, INCLUDE_DIRECTIVE, CONFIG_DIRECTIVE].contains(&tokens[SCENE_SCRIPT_INDEX]) {
+            self.close_config_block(script_path);
+        }
+
+        if self.open_config_block.is_some() {
+            if tokens[SCENE_SCRIPT_INDEX] == END_DIRECTIVE {
+                self.open_config_block = None;
+                self.warn_extra_block_tokens(line, &tokens, script_path, (source_line, first_column));
+            } else if let Some(ScriptEntry { inline_config: Some(inline_config), .. }) = config.last_mut() {
+                // Kept as written, to be parsed as a config line when the fragment is prepared
+                inline_config.push((source_line, first_column, line.to_string()));
+            }
+            return Ok(());
+        }
+
+
+        if tokens[SCENE_SCRIPT_INDEX] == SCENE_DIRECTIVE {
             // Case 1: [scene] title
             if tokens.len() == SCENE_SCRIPT_LENGTH - 1 {
                 // No scene title provided
-                if WHINGE_MODE.load(Ordering::SeqCst) {
-                    eprintln!("Warning: [scene] without a scene title");
-                }
-                return;
+                self.warn_script(DiagnosticKind::SceneWithoutTitle, script_path, (source_line, first_column),
+                                 "[scene] without a scene title".to_string());
             } else {
                 // Concatenate remaining tokens as scene title
                 let scene_title = tokens[1..].join(" ");
-                config.push((true, scene_title));
+                config.push(self.entry(ScriptEntryKind::SceneTitle, scene_title, script_path, source_line, None));
+            }
+        } else if tokens[SCENE_SCRIPT_INDEX] == INCLUDE_DIRECTIVE {
+            // Case 2: [include] script
+            let Some(tokens) = self.tokenize_script_line(line, script_path, (source_line, first_column)) else {
+                return Ok(());
+            };
+            if tokens.len() < INCLUDE_SCRIPT_LENGTH {
+                self.warn_script(DiagnosticKind::IncludeWithoutFile, script_path, (source_line, first_column),
+                                 "[include] without a script file name".to_string());
+                return Ok(());
             }
+            if tokens.len() > INCLUDE_SCRIPT_LENGTH {
+                self.warn_script(DiagnosticKind::ExtraScriptTokens, script_path,
+                                 (source_line, column_of(line, tokens[INCLUDE_SCRIPT_LENGTH].1, first_column)),
+                                 format!("Extra tokens after included script name: '{}'",
+                                         as_written(&tokens[INCLUDE_SCRIPT_LENGTH..])));
+            }
+            self.include(&resolve_relative(script_path, &tokens[1].0), script_path, source_line, config)?;
+        } else if tokens[SCENE_SCRIPT_INDEX] == CONFIG_DIRECTIVE {
+            // Case 3: [config] block
+            self.warn_extra_block_tokens(line, &tokens, script_path, (source_line, first_column));
+            config.push(self.entry(ScriptEntryKind::Config, String::new(), script_path, source_line, Some(Vec::new())));
+            self.open_config_block = Some((source_line, first_column));
+        } else if tokens[SCENE_SCRIPT_INDEX] == END_DIRECTIVE {
+            self.warn_script(DiagnosticKind::EndWithoutConfig, script_path, (source_line, first_column),
+                             "[end] without a [config] line before it".to_string());
         } else {
-            // Case 2: config filename
-            let config_filename = tokens[CONFIG_FILENAME_INDEX].to_string();
-            config.push((false, config_filename));
-            
-            if tokens.len() > CONFIG_SCRIPT_LENGTH && WHINGE_MODE.load(Ordering::SeqCst) {
-                eprintln!("Warning: Extra tokens after configuration file name: '{}'", tokens[1..].join(" "));
+            // Case 4: config filename, which may be quoted
+            let Some(tokens) = self.tokenize_script_line(line, script_path, (source_line, first_column)) else {
+                return Ok(());
+            };
+            if tokens.len() > CONFIG_SCRIPT_LENGTH {
+                self.warn_script(DiagnosticKind::ExtraScriptTokens, script_path,
+                                 (source_line, column_of(line, tokens[1].1, first_column)),
+                                 format!("Extra tokens after configuration file name: '{}'", as_written(&tokens[1..])));
             }
+
+            // Problems found on the line come before those found loading the fragment
+            let config_filename = tokens[CONFIG_FILENAME_INDEX].0.clone();
+            config.push(self.entry(ScriptEntryKind::Config, config_filename, script_path, source_line, None));
+        }
+        Ok(())
+    }
+
+    /// A script entry for `script_path:source_line`, noting the [include] lines being read and the
+    /// diagnostics collected so far
+    fn entry(&self, kind: ScriptEntryKind, text: String, script_path: &Path, source_line: usize,
+             inline_config: Option<FileLines>) -> ScriptEntry {
+        ScriptEntry {
+            kind,
+            text,
+            source_line,
+            script_path: script_path.to_path_buf(),
+            inline_config,
+            includes: self.includes.clone(),
+            diagnostics_before: self.diagnostics.len(),
+        }
+    }
+
+    /// Splits a line naming a file into tokens (see tokenizer.rs), recording a diagnostic and
+    /// giving None if a quote is never closed
+    fn tokenize_script_line<'a>(&mut self, line: &'a str, script_path: &Path,
+                                (source_line, first_column): (usize, usize)) -> Option<Tokens<'a>> {
+        match tokenize(line) {
+            Ok(tokens) => Some(tokens),
+            Err(unterminated) => {
+                self.warn_script(DiagnosticKind::UnterminatedQuote, script_path,
+                                 (source_line, column_of(line, unterminated, first_column)),
+                                 format!("Quote is never closed: {}", unterminated));
+                None
+            }
+        }
+    }
+
+    /// Records a diagnostic for tokens after a [config] or [end] directive
+    fn warn_extra_block_tokens(&mut self, line: &str, tokens: &[&str], script_path: &Path,
+                               (source_line, first_column): (usize, usize)) {
+        if tokens.len() > BLOCK_DIRECTIVE_LENGTH {
+            self.warn_script(DiagnosticKind::ExtraScriptTokens, script_path,
+                             (source_line, column_of(line, tokens[BLOCK_DIRECTIVE_LENGTH], first_column)),
+                             format!("Extra tokens after {}: '{}'", tokens[SCENE_SCRIPT_INDEX],
+                                     tokens[BLOCK_DIRECTIVE_LENGTH..].join(" ")));
         }
     }
 
+    /// Ends a [config] block still open at the end of a script file or at a [scene], [include] or
+    /// [config] line, which ends it just as [end] would
+    fn close_config_block(&mut self, script_path: &Path) {
+        if let Some(location) = self.open_config_block.take() {
+            self.warn_script(DiagnosticKind::ConfigWithoutEnd, script_path, location,
+                             "[config] block is not closed by an [end] line".to_string());
+        }
+    }
+
+    /// Follows an [include] at `script_path:source_line`:
+    /// - Fails if the included script is already being read, listing the chain of includes
+    /// - An included script that cannot be read is treated like a config file that cannot be read
+    ///   (recorded and skipped in keep-going mode), its error naming the [include] lines that led
+    ///   to it; one with no lines simply adds nothing
+    fn include(&mut self, included: &Path, script_path: &Path, source_line: usize,
+               config: &mut ScriptConfig) -> Result<(), PlayError> {
+        // Compare canonical paths so that different spellings of the same file are caught
+        let identity = |path: &Path| match self.bundle {
+            Some(_) => normalize(path),
+            None => fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+        };
+        let mut chain: Vec<PathBuf> = self.includes.iter().map(|(script, _)| script.clone()).collect();
+        chain.push(script_path.to_path_buf());
+        if chain.iter().any(|script| identity(script) == identity(included)) {
+            chain.push(included.to_path_buf());
+            return Err(PlayError::IncludeCycle { chain });
+        }
+
+        let mut script_lines: FileLines = Vec::new();
+        if let Err(error) = grab_lines(self.bundle.as_deref(), included, &mut script_lines) {
+            let chain = include_chain(script_path, source_line, &self.includes);
+            return self.diagnostics.tolerate(PlayError::Included { error: Box::new(error), chain }, script_path, source_line);
+        }
+        self.script_files.push(included.to_path_buf());
+
+        self.includes.push((script_path.to_path_buf(), source_line));
+        let result = script_lines.iter().try_for_each(|(included_line, first_column, line)| {
+            self.add_config(line, included, (*included_line, *first_column), config)
+        });
+        self.close_config_block(included);
+        self.includes.pop();
+        result
+    }
+
     /// Parses the script file line-by-line into a ScriptConfig
-    pub fn read_config(&mut self, script_filename: &String, config: &mut ScriptConfig) -> Result<(), u8> {
-        let mut script_lines: Vec<String> = Vec::new();
+    pub fn read_config(&mut self, script_filename: &Path, config: &mut ScriptConfig) -> Result<(), PlayError> {
+        let mut script_lines: FileLines = Vec::new();
         
-        if let Err(error_code) = grab_trimmed_file_lines(script_filename, &mut script_lines) {
-            return Err(error_code);
-        }
+        grab_lines(self.bundle.as_deref(), script_filename, &mut script_lines)?;
+        self.script_files.push(script_filename.to_path_buf());
 
         if script_lines.is_empty() {
-            eprintln!("Error: Script file '{}' contains no lines", script_filename);
-            return Err(SCRIPT_PARSING_ERROR);
+            return Err(PlayError::EmptyScript { path: script_filename.to_path_buf() });
         }
         
-        for line in &script_lines {
-            self.add_config(line, config);
+        for (source_line, first_column, line) in &script_lines {
+            self.add_config(line, script_filename, (*source_line, *first_column), config)?;
         }
+        self.close_config_block(script_filename);
         
         Ok(())
     }
@@ -112,54 +504,162 @@ impl Play {
     /// - Reads the script configuration file
     /// - Parses it into scene fragments
     /// - Validates that at least one fragment exists and the first has a title
-    pub fn prepare(&mut self, script_filename: &String) -> Result<(), u8> {
-        let mut config: ScriptConfig = Vec::new();
-        
-        if let Err(error_code) = self.read_config(script_filename, &mut config) {
-            return Err(error_code);
+    /// - In keep-going mode, fails with every file that could not be loaded
+    /// - In strict mode, fails with every warning that was collected
+    /// - A bundle file (see bundle.rs) is loaded as the script it holds, with every file read from the bundle
+    pub fn prepare<P: AsRef<Path>>(&mut self, script_filename: P) -> Result<(), PlayError> {
+        let script_filename = script_filename.as_ref();
+        if Bundle::is_bundle(script_filename) {
+            return self.prepare_bundle(Arc::new(Bundle::load(script_filename)?));
         }
+        self.prepare_script(script_filename)
+    }
+
+    /// Loads the play held in a bundle as prepare() loads a script, reading no files outside the bundle
+    pub fn prepare_bundle(&mut self, bundle: Arc<Bundle>) -> Result<(), PlayError> {
+        let script_filename = bundle.script_path();
+        self.bundle = Some(bundle);
+        self.prepare_script(&script_filename)
+    }
+
+    fn prepare_script(&mut self, script_filename: &Path) -> Result<(), PlayError> {
+        let mut config: ScriptConfig = Vec::new();
+        self.script_filename = script_filename.to_path_buf();
         
-        if let Err(error_code) = self.process_config(&config) {
-            return Err(error_code);
-        }
+        self.read_config(script_filename, &mut config)?;
+        self.process_config(&config)?;
 
         if self.fragments.is_empty() {
-            eprintln!("Error: No scene fragments were created");
-            return Err(SCRIPT_PARSING_ERROR);
+            return Err(PlayError::NoFragments { path: script_filename.to_path_buf() });
         }
         
         if !self.fragments[0].has_title() {
-            eprintln!("Error: First fragment must have a title");
-            return Err(SCRIPT_PARSING_ERROR);
+            return Err(PlayError::UntitledFirstFragment {
+                path: script_filename.to_path_buf(),
+                config: self.fragments[0].config_filename().display().to_string(),
+            });
+        }
+
+        if self.diagnostics.error_count() > 0 {
+            return Err(PlayError::UnreadableFiles {
+                path: script_filename.to_path_buf(),
+                diagnostics: self.diagnostics.iter().filter(|d| d.severity == Severity::Error).cloned().collect(),
+            });
+        }
+
+        if self.strict && !self.diagnostics.is_empty() {
+            return Err(PlayError::StrictModeViolations {
+                path: script_filename.to_path_buf(),
+                diagnostics: self.diagnostics.iter().cloned().collect(),
+            });
         }
         
         Ok(())
     }
 
-    ///  Executes the play:
+    /// Executes the play on standard output
+    pub fn recite(&mut self) -> Result<(), PlayError> {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        self.recite_to(&mut out)
+    }
+
+    ///  Executes the play, writing the performance to the given sink:
     /// - Handles player entrances 
     /// - Each fragment recites its lines
     /// - Handles player exits 
-    pub fn recite(&mut self) {
+    pub fn recite_to(&mut self, out: &mut dyn Write) -> Result<(), PlayError> {
+        if self.streaming {
+            return self.stream(out).and_then(|_| written(out.flush()));
+        }
+        written(self.perform(out).and_then(|_| out.flush()))
+    }
+
+    fn recite_fragment(&mut self, index: usize, out: &mut dyn Write) -> io::Result<()> {
+        if self.concurrent {
+            self.fragments[index].recite_concurrently(out)
+        } else {
+            self.fragments[index].recite(out)
+        }
+    }
+
+    fn perform(&mut self, out: &mut dyn Write) -> io::Result<()> {
         let num_fragments = self.fragments.len();
         
         for i in 0..num_fragments {
             if i == 0 {
                 // First fragment
-                self.fragments[i].enter_all();
+                self.fragments[i].enter_all(out)?;
             } else {
-                self.fragments[i].enter(&self.fragments[i - 1]);
+                self.fragments[i].enter(&self.fragments[i - 1], out)?;
             }
             
-            self.fragments[i].recite();
+            self.recite_fragment(i, out)?;
             
-            println!();
+            writeln!(out)?;
             if i == num_fragments - 1 {
                 // Final fragment
-                self.fragments[i].exit_all();
+                self.fragments[i].exit_all(out)?;
             } else {
-                self.fragments[i].exit(&self.fragments[i + 1]);
+                self.fragments[i].exit(&self.fragments[i + 1], out)?;
             }
         }
+        Ok(())
     }
-}
\ No newline at end of file
+
+    /// Performs the play in streaming mode, giving the same performance as perform():
+    /// - Loads each fragment just before reciting it, failing there if its files cannot be read
+    ///   (or, in strict mode, if loading them produced warnings)
+    /// - Keeps only the names of the players left on stage from the previous fragment, which is
+    ///   all the entrances and exits between two fragments depend on
+    /// - Drops each fragment's players and lines once it has been recited
+    fn stream(&mut self, out: &mut dyn Write) -> Result<(), PlayError> {
+        let mut previous_cast: Option<Cast> = None;
+
+        for i in 0..self.fragments.len() {
+            let config_filename = self.fragments[i].config_filename().to_path_buf();
+            self.fragments[i].prepare(&config_filename, &mut self.diagnostics)?;
+            if self.strict && !self.diagnostics.is_empty() {
+                return Err(PlayError::StrictModeViolations {
+                    path: self.script_filename.clone(),
+                    diagnostics: self.diagnostics.iter().cloned().collect(),
+                });
+            }
+
+            let fragment = &self.fragments[i];
+            match &previous_cast {
+                Some(previous_cast) => {
+                    // The previous fragment's exits had to wait for this fragment's cast
+                    written(SceneFragment::exit_cast(previous_cast, &fragment.cast_at_start(), out))?;
+                    written(fragment.enter_after(previous_cast, out))?;
+                }
+                None => written(fragment.enter_all(out))?,
+            }
+
+            written(self.recite_fragment(i, out).and_then(|_| writeln!(out)))?;
+            previous_cast = Some(self.fragments[i].cast_at_end());
+            self.fragments[i].unload();
+        }
+
+        // Final fragment
+        written(SceneFragment::exit_cast(&previous_cast.unwrap_or_default(), &[], out))
+    }
+}
+
+/// Tokens as they were written in the line, quotes and all
+fn as_written(tokens: &[(String, &str)]) -> String {
+    let raw: Vec<&str> = tokens.iter().map(|(_, raw)| *raw).collect();
+    raw.join(" ")
+}
+
+/// Reports a failure to write the performance as a PlayError
+fn written(result: io::Result<()>) -> Result<(), PlayError> {
+    result.map_err(|source| PlayError::FailedToWriteOutput { source })
+}
+
+/// The line at `script_path:source_line` and then the [include] lines around its script, innermost first
+fn include_chain(script_path: &Path, source_line: usize, includes: &Includes) -> Vec<(PathBuf, usize)> {
+    let mut chain = vec![(script_path.to_path_buf(), source_line)];
+    chain.extend(includes.iter().rev().cloned());
+    chain
+}
@@ -0,0 +1,222 @@
+/// play_error.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Defines the error type returned by every fallible step of loading a play.
+/// Each variant carries enough context (file, line, offending text, underlying I/O error)
+/// for a caller to report the failure or to react to a particular kind of failure.
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use super::diagnostics::{Diagnostic, Severity};
+use super::json::{optional_number, quote};
+
+#[derive(Debug)]
+pub enum PlayError {
+    /// A script, config or part file could not be opened
+    FailedToOpenFile { path: PathBuf, source: io::Error },
+    /// Reading the given (1-based) line of a file failed part way through
+    FailedToReadLine { path: PathBuf, line: usize, source: io::Error },
+    /// The script file has no lines at all
+    EmptyScript { path: PathBuf },
+    /// A scene config file has no lines at all
+    EmptyConfig { path: PathBuf },
+    /// A [config] block in the script at `path`, starting at `line`, has no config lines
+    EmptyInlineConfig { path: PathBuf, line: usize },
+    /// The script named no config files, so no scene fragments were created
+    NoFragments { path: PathBuf },
+    /// The first fragment of the script has no scene title; `config` is the config filename it names
+    UntitledFirstFragment { path: PathBuf, config: String },
+    /// Writing the recitation to its output sink failed
+    FailedToWriteOutput { source: io::Error },
+    /// A scene was selected that the play does not have
+    NoSuchScene { scene: usize, scene_count: usize },
+    /// Keep-going mode recorded config or part files that could not be read; all are listed
+    UnreadableFiles { path: PathBuf, diagnostics: Vec<Diagnostic> },
+    /// Strict mode is on and loading produced warnings; every one found is listed
+    StrictModeViolations { path: PathBuf, diagnostics: Vec<Diagnostic> },
+    /// A rendered script could not be split back into files; `line` is the (1-based) line it failed at
+    UnsplittableScript { path: PathBuf, line: usize, message: String },
+    /// Following [include] directives led back to a script already being read; `chain` lists the
+    /// scripts from the top-level one to the repeated one
+    IncludeCycle { chain: Vec<PathBuf> },
+    /// The bundle file at `path` is malformed at the given (1-based) line
+    InvalidBundle { path: PathBuf, line: usize, message: String },
+    /// A file used by a play being packed lies outside the script's directory
+    UnbundlableFile { path: PathBuf, directory: PathBuf },
+    /// A script reached through [include] lines, or a file it names, could not be loaded; `chain` is
+    /// the (script, source_line) naming the file and then each [include] line around that script,
+    /// innermost first
+    Included { error: Box<PlayError>, chain: Vec<(PathBuf, usize)> },
+}
+
+/// " (included from b.txt:2, a.txt:5)" for the [include] lines in `includes`, innermost first;
+/// empty if there are none
+pub fn included_from(includes: &[(PathBuf, usize)]) -> String {
+    if includes.is_empty() {
+        return String::new();
+    }
+    format!(" (included from {})", locations(includes))
+}
+
+/// "b.txt:2, a.txt:5"
+fn locations(chain: &[(PathBuf, usize)]) -> String {
+    let locations: Vec<String> = chain.iter()
+        .map(|(script, source_line)| format!("{}:{}", script.display(), source_line))
+        .collect();
+    locations.join(", ")
+}
+
+impl PlayError {
+    /// Stable identifier for the error, used in machine-readable output
+    pub fn code(&self) -> &'static str {
+        match self {
+            PlayError::FailedToOpenFile { .. } => "failed-to-open-file",
+            PlayError::FailedToReadLine { .. } => "failed-to-read-line",
+            PlayError::EmptyScript { .. } => "empty-script",
+            PlayError::EmptyConfig { .. } => "empty-config",
+            PlayError::EmptyInlineConfig { .. } => "empty-inline-config",
+            PlayError::NoFragments { .. } => "no-fragments",
+            PlayError::UntitledFirstFragment { .. } => "untitled-first-fragment",
+            PlayError::FailedToWriteOutput { .. } => "failed-to-write-output",
+            PlayError::NoSuchScene { .. } => "no-such-scene",
+            PlayError::UnreadableFiles { .. } => "unreadable-files",
+            PlayError::StrictModeViolations { .. } => "strict-mode-violations",
+            PlayError::UnsplittableScript { .. } => "unsplittable-script",
+            PlayError::IncludeCycle { .. } => "include-cycle",
+            PlayError::InvalidBundle { .. } => "invalid-bundle",
+            PlayError::UnbundlableFile { .. } => "unbundlable-file",
+            PlayError::Included { error, .. } => error.code(),
+        }
+    }
+
+    /// The error itself, without the [include] lines that led to it
+    pub fn unwrapped(&self) -> &PlayError {
+        match self {
+            PlayError::Included { error, .. } => error.unwrapped(),
+            _ => self,
+        }
+    }
+
+    /// The file the error is about, when there is one; for a cycle, the script whose [include] closes it
+    pub fn file(&self) -> Option<&Path> {
+        match self {
+            PlayError::FailedToOpenFile { path, .. }
+            | PlayError::FailedToReadLine { path, .. }
+            | PlayError::EmptyScript { path }
+            | PlayError::EmptyConfig { path }
+            | PlayError::EmptyInlineConfig { path, .. }
+            | PlayError::NoFragments { path }
+            | PlayError::UntitledFirstFragment { path, .. }
+            | PlayError::UnreadableFiles { path, .. }
+            | PlayError::StrictModeViolations { path, .. }
+            | PlayError::UnsplittableScript { path, .. }
+            | PlayError::InvalidBundle { path, .. }
+            | PlayError::UnbundlableFile { path, .. } => Some(path),
+            PlayError::IncludeCycle { chain } => chain.len().checked_sub(2).map(|index| chain[index].as_path()),
+            PlayError::FailedToWriteOutput { .. } | PlayError::NoSuchScene { .. } => None,
+            PlayError::Included { error, .. } => error.file(),
+        }
+    }
+
+    /// The 1-based line within file() the error belongs to, when it belongs to a single line
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            PlayError::FailedToReadLine { line, .. }
+            | PlayError::EmptyInlineConfig { line, .. }
+            | PlayError::UnsplittableScript { line, .. }
+            | PlayError::InvalidBundle { line, .. } => Some(*line),
+            PlayError::Included { error, .. } => error.line(),
+            _ => None,
+        }
+    }
+
+    /// A single-line JSON object shaped like Diagnostic::to_json(), with severity "error"
+    pub fn to_json(&self) -> String {
+        let file = self.file().map_or("null".to_string(), |file| quote(&file.display().to_string()));
+        format!("{{\"severity\":{},\"code\":{},\"file\":{},\"line\":{},\"column\":null,\"message\":{}}}",
+                quote(Severity::Error.name()), quote(self.code()), file, optional_number(self.line()),
+                quote(&self.to_string()))
+    }
+}
+
+fn write_locations(f: &mut fmt::Formatter<'_>, diagnostics: &[Diagnostic]) -> fmt::Result {
+    for diagnostic in diagnostics {
+        write!(f, "\n    {}: {}", diagnostic.location(), diagnostic.message)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for PlayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayError::FailedToOpenFile { path, source } => {
+                write!(f, "Failed to open file '{}': {}", path.display(), source)
+            }
+            PlayError::FailedToReadLine { path, line, source } => {
+                write!(f, "Failed to read line {} from file '{}': {}", line, path.display(), source)
+            }
+            PlayError::EmptyScript { path } => {
+                write!(f, "Script file '{}' contains no lines", path.display())
+            }
+            PlayError::EmptyConfig { path } => {
+                write!(f, "Config file '{}' contains no lines", path.display())
+            }
+            PlayError::EmptyInlineConfig { path, line } => {
+                write!(f, "[config] block at line {} of '{}' contains no lines", line, path.display())
+            }
+            PlayError::NoFragments { path } => {
+                write!(f, "No scene fragments were created from '{}'", path.display())
+            }
+            PlayError::UntitledFirstFragment { path, config } => {
+                write!(f, "First fragment must have a title ('{}' in '{}')", config, path.display())
+            }
+            PlayError::FailedToWriteOutput { source } => {
+                write!(f, "Failed to write recitation output: {}", source)
+            }
+            PlayError::NoSuchScene { scene, scene_count } => {
+                write!(f, "There is no scene {} (the play has {} scene(s))", scene, scene_count)
+            }
+            PlayError::UnreadableFiles { path, diagnostics } => {
+                write!(f, "{} file(s) named by '{}' could not be loaded:", diagnostics.len(), path.display())?;
+                write_locations(f, diagnostics)
+            }
+            PlayError::StrictModeViolations { path, diagnostics } => {
+                write!(f, "Strict mode found {} problem(s) in '{}':", diagnostics.len(), path.display())?;
+                write_locations(f, diagnostics)
+            }
+            PlayError::UnsplittableScript { path, line, message } => {
+                write!(f, "Cannot split rendered script '{}' at line {}: {}", path.display(), line, message)
+            }
+            PlayError::IncludeCycle { chain } => {
+                let names: Vec<String> = chain.iter().map(|path| format!("'{}'", path.display())).collect();
+                write!(f, "Scripts include each other in a cycle: {}", names.join(" -> "))
+            }
+            PlayError::InvalidBundle { path, line, message } => {
+                write!(f, "Invalid bundle '{}' at line {}: {}", path.display(), line, message)
+            }
+            PlayError::UnbundlableFile { path, directory } => {
+                write!(f, "Cannot bundle '{}': it is outside the script's directory '{}'",
+                       path.display(), directory.display())
+            }
+            PlayError::Included { error, chain } => {
+                write!(f, "{} (named at {}", error, locations(&chain[..1]))?;
+                if chain.len() > 1 {
+                    write!(f, ", included from {}", locations(&chain[1..]))?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl Error for PlayError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PlayError::FailedToOpenFile { source, .. } => Some(source),
+            PlayError::FailedToReadLine { source, .. } => Some(source),
+            PlayError::FailedToWriteOutput { source } => Some(source),
+            PlayError::Included { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -2,38 +2,121 @@
 /// Author: Zichu Pan, Edgar Palomino
 /// Summary: This module implements the Player structure that represents individual actors/characters in a play, 
 /// managing their dialogue lines and delivery.
-use std::sync::atomic::Ordering;
-use super::declarations::WHINGE_MODE;
-use super::script_gen::grab_trimmed_file_lines;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use super::bundle::{grab_lines, Bundle};
+use super::diagnostics::{Diagnostics, DiagnosticKind};
+use super::play_error::PlayError;
+use super::script_gen::FileLines;
 
-pub type PlayLines = Vec<(usize, String)>; // (line_number, line_text)
+pub type PlayLines = Vec<(usize, String, usize)>; // (line_number, line_text, source_line)
+
+// A part file line starting with this marker continues the numbered line above it
+pub const CONTINUATION_MARKER: &str = "|";
+
+// A numbered line whose text is wrapped in these is a stage direction rather than dialogue
+pub const STAGE_DIRECTION_OPEN: char = '[';
+pub const STAGE_DIRECTION_CLOSE: char = ']';
+
+/// Whether a line's text is a stage direction such as "[Draws sword]"
+pub fn is_stage_direction(text: &str) -> bool {
+    text.starts_with(STAGE_DIRECTION_OPEN) && text.ends_with(STAGE_DIRECTION_CLOSE)
+}
+
+// Words of a part name may be separated by this instead of a space, e.g. First_Witch
+pub const NAME_WORD_SEPARATOR: char = '_';
+
+/// The character a part name stands for: case, and whether words are separated by spaces or
+/// underscores, do not matter, so "First Witch", "first witch" and "FIRST_WITCH" are one character
+pub fn canonical_name(name: &str) -> String {
+    let words: Vec<String> = name.split(|c: char| c.is_whitespace() || c == NAME_WORD_SEPARATOR)
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    words.join(" ")
+}
+
+// Value of an enter=/exit= config option that places the cue at the player's first/last line
+pub const AUTO_CUE: &str = "auto";
+
+/// When a player enters or exits partway through a scene fragment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cue {
+    /// Enters just before / exits just after the given line number
+    Line(usize),
+    /// Enters just before the player's first line / exits just after their last line
+    Auto,
+}
+
+impl Cue {
+    /// Parses the value of an enter=/exit= config option: a line number or "auto"
+    pub fn from_token(token: &str) -> Option<Cue> {
+        if token == AUTO_CUE {
+            return Some(Cue::Auto);
+        }
+        token.parse::<usize>().ok().map(Cue::Line)
+    }
+}
 
 pub struct Player {
     name: String,
+    identity: String,
+    part_filename: PathBuf,
     lines: PlayLines,
     index: usize,
+    entrance: Option<Cue>,
+    exit: Option<Cue>,
+    bundle: Option<Arc<Bundle>>,
 }
 
 impl Player {
-    // Create a new player
-    pub fn new(name: &String) -> Player {
+    // Create a new player; the name is both printed and, in canonical form, identifies the character
+    pub fn new(name: &str) -> Player {
         Player {
-            name: name.clone(),
+            name: name.to_string(),
+            identity: canonical_name(name),
+            part_filename: PathBuf::new(),
             lines: PlayLines::new(),
             index: 0,
+            entrance: None,
+            exit: None,
+            bundle: None,
         }
     }
 
+    /// Prints the player under a different name, without changing which character they are
+    pub fn set_display_name(&mut self, display_name: &str) {
+        self.name = display_name.to_string();
+    }
+
+    /// Makes the player enter partway through the fragment instead of at its start
+    pub fn set_entrance(&mut self, entrance: Option<Cue>) {
+        self.entrance = entrance;
+    }
+
+    /// Makes the player exit partway through the fragment instead of at its end
+    pub fn set_exit(&mut self, exit: Option<Cue>) {
+        self.exit = exit;
+    }
+
+    /// Makes prepare() read the part file from a bundle instead of the file system
+    pub fn set_bundle(&mut self, bundle: Option<Arc<Bundle>>) {
+        self.bundle = bundle;
+    }
+
     /// Parses individual script lines:
     /// - Expects format: <line_number> <dialogue_text>
     /// - Extracts line number from first token
     /// - Stores the remaining text as dialogue
-    /// - Warns about invalid line numbers in whinge mode
+    /// - Records a diagnostic for invalid line numbers
     /// - Ignores empty lines
-    fn add_script_line(&mut self, line: &String) {
+    /// - Returns whether a numbered line was added, i.e. whether a continuation line may follow
+    fn add_script_line(&mut self, line: &str, part_path: &Path, (source_line, first_column): (usize, usize),
+                       diagnostics: &mut Diagnostics) -> bool {
         // Ignore empty lines
         if line.is_empty() {
-            return;
+            return false;
         }
 
         if let Some((first_token, rest_of_line)) = line.split_once(char::is_whitespace) {
@@ -42,31 +125,63 @@ impl Player {
                 // Try to parse the first token as line number
                 match first_token.parse::<usize>() {
                     Ok(line_number) => {
-                        self.lines.push((line_number, trimmed_rest.to_string()));
+                        self.lines.push((line_number, trimmed_rest.to_string(), source_line));
+                        return true;
                     }
                     Err(_error_code) => {
-                        if WHINGE_MODE.load(Ordering::SeqCst) {
-                            eprintln!("Warning: '{}' does not represent a valid line number", first_token);
-                        }
+                        diagnostics.warn(DiagnosticKind::InvalidLineNumber, part_path, Some(source_line),
+                                         Some(first_column),
+                                         format!("'{}' does not represent a valid line number", first_token));
                     }
                 }
             }
+        false
+    }
+
+    /// Appends a continuation line to the numbered line added just before it:
+    /// - The text after the marker becomes a new line of the same speech
+    /// - Records a diagnostic and drops the text if there is no numbered line to continue,
+    ///   e.g. at the top of the file or after an invalid line number
+    /// - Records a diagnostic and drops the line if it has no text, since an empty line in a speech
+    ///   would print as a blank line, which reads as the end of the speech; the speech goes on after it
+    fn add_continuation_line(&mut self, text: &str, continuable: bool, part_path: &Path,
+                             (source_line, first_column): (usize, usize), diagnostics: &mut Diagnostics) {
+        match self.lines.last_mut() {
+            Some(_) if continuable && text.is_empty() => {
+                diagnostics.warn(DiagnosticKind::EmptyContinuationLine, part_path, Some(source_line),
+                                 Some(first_column), "Continuation line has no text".to_string());
+            }
+            Some((_, line_text, _)) if continuable => {
+                line_text.push('\n');
+                line_text.push_str(text);
+            }
+            _ => {
+                diagnostics.warn(DiagnosticKind::StrayContinuationLine, part_path, Some(source_line),
+                                 Some(first_column),
+                                 format!("Continuation line does not follow a numbered line: '{}'", text));
+            }
+        }
     }
 
     /// Loads the player's script:
     /// - Reads lines from the character's script file
-    /// - Parses each line using add_script_line()
+    /// - Parses each line using add_script_line(), appending continuation lines to the line above
     /// - Sorts lines by line number to handle out-of-order input
-    pub fn prepare(&mut self, part_filename: &String) -> Result<(), u8> {
-        let mut part_lines: Vec<String> = Vec::new();
+    pub fn prepare(&mut self, part_filename: &Path, diagnostics: &mut Diagnostics) -> Result<(), PlayError> {
+        let mut part_lines: FileLines = Vec::new();
+        self.part_filename = part_filename.to_path_buf();
         
-        if let Err(error_code) = grab_trimmed_file_lines(part_filename, &mut part_lines) {
-            return Err(error_code);
-        }
+        grab_lines(self.bundle.as_deref(), part_filename, &mut part_lines)?;
 
-        // Process each line and add to player's lines
-        for line in &part_lines {
-            self.add_script_line(line);
+        // Process each line and add to player's lines; blank lines end a multi-line speech
+        let mut continuable = false;
+        for (source_line, first_column, line) in &part_lines {
+            let location = (*source_line, *first_column);
+            if let Some(text) = line.strip_prefix(CONTINUATION_MARKER) {
+                self.add_continuation_line(text.trim(), continuable, part_filename, location, diagnostics);
+            } else {
+                continuable = self.add_script_line(line, part_filename, location, diagnostics);
+            }
         }
 
         // Sort lines by line number to handle out-of-order lines
@@ -78,25 +193,35 @@ impl Player {
 
     /// Delivers the next line of dialogue:
     /// - Checks if all lines have been spoken
-    /// - Prints character name if speaker changes
-    /// - Prints the dialogue text
+    /// - Prints a stage direction on its own, without a speaker name, so whoever speaks next is named again
+    /// - Prints character name if speaker changes; `current_speaker` holds the identity of whoever spoke last
+    /// - Prints the dialogue text, which may span several lines
     /// - Advances the index to next line
-    pub fn speak(&mut self, current_speaker: &mut String) {
+    pub fn speak(&mut self, current_speaker: &mut String, out: &mut dyn Write) -> io::Result<()> {
         // return if all lines have already been spoken
         if self.index >= self.lines.len() {
-            return;
+            return Ok(());
+        }
+
+        if is_stage_direction(&self.lines[self.index].1) {
+            current_speaker.clear();
+            writeln!(out)?;
+            writeln!(out, "{}", self.lines[self.index].1)?;
+            self.index += 1;
+            return Ok(());
         }
 
         // Check if this player is different from the current speaker
-        if *current_speaker != self.name {
-            // Update the current speaker to this player's name
-            *current_speaker = self.name.clone();
-            println!();
-            println!("{}.", self.name);
+        if *current_speaker != self.identity {
+            // Update the current speaker to this player's character
+            *current_speaker = self.identity.clone();
+            writeln!(out)?;
+            writeln!(out, "{}.", self.name)?;
         }
 
-        println!("{}", self.lines[self.index].1);
+        writeln!(out, "{}", self.lines[self.index].1)?;
         self.index += 1;
+        Ok(())
     }
 
     pub fn next_line(&self) -> Option<usize> {
@@ -107,9 +232,48 @@ impl Player {
         }
     }
 
+    /// (line_number, source_line) of each of this player's lines, in the order they will be spoken
+    pub fn line_locations(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.lines.iter().map(|(line_number, _, source_line)| (*line_number, *source_line))
+    }
+
+    /// Line number before which the player enters mid-scene, or None if they are on stage from
+    /// the start of the fragment (an automatic cue for a player with no lines counts as None)
+    pub fn entrance_line(&self) -> Option<usize> {
+        match self.entrance? {
+            Cue::Line(line_number) => Some(line_number),
+            Cue::Auto => self.lines.first().map(|(line_number, _, _)| *line_number),
+        }
+    }
+
+    /// Line number after which the player exits mid-scene, or None if they stay on stage until
+    /// the end of the fragment (an automatic cue for a player with no lines counts as None)
+    pub fn exit_line(&self) -> Option<usize> {
+        match self.exit? {
+            Cue::Line(line_number) => Some(line_number),
+            Cue::Auto => self.lines.last().map(|(line_number, _, _)| *line_number),
+        }
+    }
+
+    /// The part file this player was prepared from
+    pub fn part_filename(&self) -> &Path {
+        &self.part_filename
+    }
+
+    /// The name printed for the player
     pub fn name(&self) -> &String {
         &self.name
     }
+
+    /// The canonical name of the character the player is, compared to recognize them across fragments
+    pub fn identity(&self) -> &String {
+        &self.identity
+    }
+
+    /// All of this player's lines, sorted by line number
+    pub fn lines(&self) -> &PlayLines {
+        &self.lines
+    }
     
 }
 
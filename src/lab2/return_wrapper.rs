@@ -1,23 +1,61 @@
 /// return_wrapper.rs
 /// Author: Zichu Pan, Edgar Palomino
 use std::process::{ExitCode, Termination};
-use super::declarations::SUCCESS;
+use super::declarations::{SUCCESS, BAD_COMMAND_LINE_ERROR, FAILED_TO_OPEN_FILE, FAILED_TO_READ_LINE_FROM_FILE,
+                          SCRIPT_PARSING_ERROR, CONFIG_PARSING_ERROR, FAILED_TO_WRITE_OUTPUT,
+                          STRICT_MODE_ERROR};
+use super::play_error::PlayError;
 
 pub struct ReturnWrapper {
     code: u8,
+    quiet: bool,
 }
 
 impl ReturnWrapper {
     pub fn new(code: u8) -> ReturnWrapper {
-        ReturnWrapper { code }
+        ReturnWrapper { code, quiet: false }
+    }
+
+    /// A quiet wrapper exits with its code without printing it, e.g. when errors are reported as JSON
+    pub fn set_quiet(&mut self, quiet: bool) {
+        self.quiet = quiet;
+    }
+
+    pub fn code(&self) -> u8 {
+        self.code
+    }
+}
+
+/// Maps each kind of failure onto the exit codes declared in declarations.rs
+impl From<&PlayError> for ReturnWrapper {
+    fn from(error: &PlayError) -> ReturnWrapper {
+        let code = match error {
+            PlayError::FailedToOpenFile { .. } => FAILED_TO_OPEN_FILE,
+            PlayError::FailedToReadLine { .. } => FAILED_TO_READ_LINE_FROM_FILE,
+            PlayError::EmptyScript { .. } => SCRIPT_PARSING_ERROR,
+            PlayError::NoFragments { .. } => SCRIPT_PARSING_ERROR,
+            PlayError::UntitledFirstFragment { .. } => SCRIPT_PARSING_ERROR,
+            PlayError::EmptyConfig { .. } => CONFIG_PARSING_ERROR,
+            PlayError::EmptyInlineConfig { .. } => CONFIG_PARSING_ERROR,
+            PlayError::FailedToWriteOutput { .. } => FAILED_TO_WRITE_OUTPUT,
+            PlayError::NoSuchScene { .. } => BAD_COMMAND_LINE_ERROR,
+            PlayError::UnreadableFiles { .. } => FAILED_TO_OPEN_FILE,
+            PlayError::StrictModeViolations { .. } => STRICT_MODE_ERROR,
+            PlayError::UnsplittableScript { .. } => SCRIPT_PARSING_ERROR,
+            PlayError::IncludeCycle { .. } => SCRIPT_PARSING_ERROR,
+            PlayError::InvalidBundle { .. } => SCRIPT_PARSING_ERROR,
+            PlayError::UnbundlableFile { .. } => SCRIPT_PARSING_ERROR,
+            PlayError::Included { error, .. } => return ReturnWrapper::from(error.as_ref()),
+        };
+        ReturnWrapper::new(code)
     }
 }
 
 impl Termination for ReturnWrapper {
     fn report(self) -> ExitCode {
-        if self.code != SUCCESS {
+        if self.code != SUCCESS && !self.quiet {
             eprintln!("Error: {}", self.code);
         }
         ExitCode::from(self.code)
     }
-}
\ No newline at end of file
+}
@@ -2,222 +2,594 @@
 /// Author: Zichu Pan, Edgar Palomino
 /// Summary: This module  implements the SceneFragment structure that represents individual scenes within a play, 
 /// managing players (actors) and their dialogue.
-use std::sync::atomic::Ordering;
-use super::player::Player;
-use super::declarations::{WHINGE_MODE, CONFIG_PARSING_ERROR};
-use super::script_gen::grab_trimmed_file_lines;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use super::bundle::{grab_lines, Bundle};
+use super::player::{Cue, Player};
+use super::diagnostics::{Diagnostics, DiagnosticKind};
+use super::parallel::map_in_order;
+use super::play_error::PlayError;
+use super::script_gen::{column_of, resolve_relative, FileLines};
+use super::tokenizer::tokenize;
 
-pub type PlayConfig = Vec<(String, String)>; // (part_name, part_filename)
+pub type Cues = (Option<Cue>, Option<Cue>); // (entrance, exit)
+pub(crate) type PartConfig = (String, String, usize, Cues, Option<String>); // (part_name, part_filename, source_line, cues, display_name)
+pub type PlayConfig = Vec<PartConfig>;
+pub(crate) type PreparedPlayer = (Player, Diagnostics, Result<(), PlayError>); // (player, part_diagnostics, result)
+pub type Cast = Vec<(String, String)>; // (identity, name) of each player on stage
+type CueLines = Vec<(usize, usize)>; // (line_number, player_index)
+type LineQueue = BinaryHeap<Reverse<(usize, usize)>>; // min-heap of (next_line_number, player_index)
+type Delivery = io::Result<(Vec<u8>, String, Option<usize>)>; // (rendered_line, current_speaker, next_line_number)
       
 pub const PART_NAME_INDEX: usize = 0;
 pub const PART_FILENAME_INDEX: usize = 1;
 pub const CONFIG_LINE_TOKEN_COUNT: usize = 2;
 
+// Optional key=value tokens after the part filename on a config line
+pub const OPTION_SEPARATOR: char = '=';
+pub const ENTER_OPTION: &str = "enter";
+pub const EXIT_OPTION: &str = "exit";
+pub const NAME_OPTION: &str = "name";
+
 pub struct SceneFragment {
     title: String,
+    scene: usize,
+    config_filename: PathBuf,
+    // (source_line, config_lines) of a [config] block in the script, read instead of a config file
+    inline_config: Option<(usize, FileLines)>,
+    bundle: Option<Arc<Bundle>>,
+    // The script line naming the config and the [include] lines around that script, innermost first,
+    // when the script was included
+    included_from: Vec<(PathBuf, usize)>,
     players: Vec<Player>,
 }
 
 impl SceneFragment {
-    pub fn new(title: &String) -> SceneFragment {
+    /// Creates a fragment of the given (1-based) scene of the play
+    pub fn new(title: &str, scene: usize) -> SceneFragment {
         SceneFragment {
-            title: title.clone(),
+            title: title.to_string(),
+            scene,
+            config_filename: PathBuf::new(),
+            inline_config: None,
+            bundle: None,
+            included_from: Vec::new(),
             players: Vec::new(),
         }
     }
 
-    /// Instantiates Player objects:
-    /// - Creates a Player for each character
-    /// - Calls prepare() on each player with their script file
-    pub fn process_config(&mut self, config: &PlayConfig) -> Result<(), u8> {
-        for config_entry in config {
-            match config_entry {
-                (part_name, part_filename) => {
-                    // Create a new Player instance using the part name
-                    let mut player = Player::new(part_name);
-                    
-                    // Call prepare on the player with the part filename
-                    if let Err(error_code) = player.prepare(part_filename) {
-                        return Err(error_code);
-                    }
-                    
-                    // Push the prepared player into the Play's vector
-                    self.players.push(player);
-                }
+    /// Creates the Player for one config line and prepares it from its part file, resolved relative
+    /// to the config file; its diagnostics are collected apart, so parts can be read on any thread
+    pub(crate) fn prepare_player(&self, (part_name, part_filename, _, (entrance, exit), display_name): &PartConfig,
+                          diagnostics: &Diagnostics) -> PreparedPlayer {
+        // Create a new Player instance using the part name
+        let mut player = Player::new(part_name);
+        if let Some(display_name) = display_name {
+            player.set_display_name(display_name);
+        }
+        player.set_entrance(*entrance);
+        player.set_exit(*exit);
+        player.set_bundle(self.bundle.clone());
+
+        // Call prepare on the player with the part filename
+        let mut part_diagnostics = diagnostics.fork();
+        let result = player.prepare(&resolve_relative(&self.config_filename, part_filename), &mut part_diagnostics);
+        (player, part_diagnostics, result)
+    }
+
+    /// Adds a player given by prepare_player() for the config line `source_line`, after its diagnostics:
+    /// - Checks the player's cues against each other and its lines
+    /// - Reports a part that could not be read against the config line, stopping unless that is tolerated
+    pub(crate) fn add_player(&mut self, (player, part_diagnostics, result): PreparedPlayer, source_line: usize,
+                      diagnostics: &mut Diagnostics) -> Result<(), PlayError> {
+        diagnostics.merge(part_diagnostics);
+        match result {
+            // Push the prepared player into the Play's vector
+            Ok(()) => {
+                Self::check_cues(&player, &self.config_filename, source_line, diagnostics);
+                self.players.push(player);
+                Ok(())
             }
+            Err(error) => diagnostics.tolerate(error, &self.config_filename, source_line),
+        }
+    }
+
+    /// Instantiates Player objects:
+    /// - Creates a Player for each character with prepare_player()
+    /// - The part files are read concurrently, then the players are added in config order with add_player()
+    /// - Warnings and errors are reported in config order, and stop at the first error that is not
+    ///   tolerated just as they would if the parts were read one at a time
+    pub fn process_config(&mut self, config: &PlayConfig, diagnostics: &mut Diagnostics) -> Result<(), PlayError> {
+        let prepared = map_in_order(config, |part_config| self.prepare_player(part_config, diagnostics));
+
+        for (player, (_, _, source_line, _, _)) in prepared.into_iter().zip(config) {
+            self.add_player(player, *source_line, diagnostics)?;
         }
         Ok(())
     }
 
-    fn add_config(&mut self, line: &String, config: &mut PlayConfig) {
-        let tokens: Vec<&str> = line.split_whitespace().collect();
-        
-        if tokens.len() != CONFIG_LINE_TOKEN_COUNT {
-            if WHINGE_MODE.load(Ordering::SeqCst) {
-                if tokens.len() < CONFIG_LINE_TOKEN_COUNT {
-                    eprintln!("Warning: Configuration line has too few tokens (expected {}, got {}): '{}'", 
-                            CONFIG_LINE_TOKEN_COUNT, tokens.len(), line);
-                } else {
-                    eprintln!("Warning: Configuration line has too many tokens (expected {}, got {}): '{}'", 
-                            CONFIG_LINE_TOKEN_COUNT, tokens.len(), line);
-                }
+    /// Records a diagnostic against the player's config line for cues that would announce the player
+    /// off stage while speaking:
+    /// - An exit cue at or before the entrance cue, i.e. exiting after line X but entering before a line after X
+    /// - An entrance cue after the player's first line, or an exit cue before their last line
+    fn check_cues(player: &Player, config_path: &Path, source_line: usize, diagnostics: &mut Diagnostics) {
+        let mut warn = |message: String| {
+            diagnostics.warn(DiagnosticKind::InvalidConfigOption, config_path, Some(source_line), None, message);
+        };
+        let first_line = player.lines().first().map(|(line_number, _, _)| *line_number);
+        let last_line = player.lines().last().map(|(line_number, _, _)| *line_number);
+
+        if let (Some(entrance), Some(exit)) = (player.entrance_line(), player.exit_line()) {
+            if exit < entrance {
+                warn(format!("{} exits after line {} but only enters before line {}", player.name(), exit, entrance));
+            }
+        }
+        if let (Some(entrance), Some(first_line)) = (player.entrance_line(), first_line) {
+            if entrance > first_line {
+                warn(format!("{} enters before line {} but speaks line {} before that", player.name(), entrance, first_line));
+            }
+        }
+        if let (Some(exit), Some(last_line)) = (player.exit_line(), last_line) {
+            if exit < last_line {
+                warn(format!("{} exits after line {} but still speaks line {}", player.name(), exit, last_line));
+            }
+        }
+    }
+
+    /// Parses a key=value option token of a config line:
+    /// - enter=/exit= give the cues, name= the name printed for the player
+    /// - Records a diagnostic for unknown keys, for cues that are neither a line number nor "auto",
+    ///   and for an empty name
+    fn add_option(token: &str, config_path: &Path, (source_line, column): (usize, usize),
+                  (cues, display_name): (&mut Cues, &mut Option<String>), diagnostics: &mut Diagnostics) {
+        let (key, value) = token.split_once(OPTION_SEPARATOR).unwrap_or((token, ""));
+        let cue = match key {
+            ENTER_OPTION => &mut cues.0,
+            EXIT_OPTION => &mut cues.1,
+            NAME_OPTION if !value.trim().is_empty() => {
+                *display_name = Some(value.to_string());
+                return;
+            }
+            NAME_OPTION => {
+                diagnostics.warn(DiagnosticKind::InvalidConfigOption, config_path, Some(source_line), Some(column),
+                                 format!("Option '{}' does not give a name", token));
+                return;
+            }
+            _ => {
+                diagnostics.warn(DiagnosticKind::InvalidConfigOption, config_path, Some(source_line), Some(column),
+                                 format!("Unknown configuration option '{}' (expected {}, {} or {})",
+                                         key, ENTER_OPTION, EXIT_OPTION, NAME_OPTION));
+                return;
+            }
+        };
+
+        match Cue::from_token(value) {
+            Some(parsed) => *cue = Some(parsed),
+            None => {
+                diagnostics.warn(DiagnosticKind::InvalidConfigOption, config_path, Some(source_line), Some(column),
+                                 format!("'{}' is not a line number or 'auto' in option '{}'", value, token));
+            }
+        }
+    }
+
+    /// Parses a config line into a part name, part filename and optional cues and display name:
+    /// - Names and filenames with spaces are written in quotes, e.g. "First Witch" (see tokenizer.rs)
+    /// - Tokens after the filename of the form key=value are enter=/exit=/name= options
+    /// - Records a diagnostic for any other extra tokens, or too few tokens
+    /// - Records a diagnostic for a quote that is never closed, and skips the line
+    fn add_config(&mut self, line: &str, config_path: &Path, (source_line, first_column): (usize, usize),
+                  config: &mut PlayConfig, diagnostics: &mut Diagnostics) {
+        let line_tokens = match tokenize(line) {
+            Ok(line_tokens) => line_tokens,
+            Err(unterminated) => {
+                diagnostics.warn(DiagnosticKind::UnterminatedQuote, config_path, Some(source_line),
+                                 Some(column_of(line, unterminated, first_column)),
+                                 format!("Quote is never closed: {}", unterminated));
+                return;
+            }
+        };
+
+        let mut tokens: Vec<(String, &str)> = Vec::new();
+        let mut cues: Cues = (None, None);
+        let mut display_name: Option<String> = None;
+        for (index, (text, raw)) in line_tokens.into_iter().enumerate() {
+            if index >= CONFIG_LINE_TOKEN_COUNT && text.contains(OPTION_SEPARATOR) {
+                Self::add_option(&text, config_path, (source_line, column_of(line, raw, first_column)),
+                                 (&mut cues, &mut display_name), diagnostics);
+            } else {
+                tokens.push((text, raw));
             }
         }
         
+        if tokens.len() < CONFIG_LINE_TOKEN_COUNT {
+            diagnostics.warn(DiagnosticKind::TooFewConfigTokens, config_path, Some(source_line), Some(first_column),
+                             format!("Configuration line has too few tokens (expected {}, got {}): '{}'", 
+                                     CONFIG_LINE_TOKEN_COUNT, tokens.len(), line));
+        } else if tokens.len() > CONFIG_LINE_TOKEN_COUNT {
+            diagnostics.warn(DiagnosticKind::TooManyConfigTokens, config_path, Some(source_line),
+                             Some(column_of(line, tokens[CONFIG_LINE_TOKEN_COUNT].1, first_column)),
+                             format!("Configuration line has too many tokens (expected {}, got {}): '{}'", 
+                                     CONFIG_LINE_TOKEN_COUNT, tokens.len(), line));
+        }
+        
         if tokens.len() >= CONFIG_LINE_TOKEN_COUNT {
             config.push((
-                tokens[PART_NAME_INDEX].to_string(),
-                tokens[PART_FILENAME_INDEX].to_string()
+                tokens[PART_NAME_INDEX].0.clone(),
+                tokens[PART_FILENAME_INDEX].0.clone(),
+                source_line,
+                cues,
+                display_name
             ));
         }
     }
 
     /// Parse configuration files:
     /// - Each line should have exactly 2 tokens: character name and their script file
-    /// - Warns about malformed lines (too few/many tokens) in whinge mode
+    /// - An inline [config] block given by set_inline_config() is parsed in place of the file, with
+    ///   `config_filename` being the script it was written in
+    /// - Records a diagnostic for malformed lines (too few/many tokens)
     /// - Builds a PlayConfig with character-to-script mappings
-    pub fn read_config(&mut self, config_filename: &String, config: &mut PlayConfig) -> Result<(), u8> {
-        let mut config_lines: Vec<String> = Vec::new();
+    /// - A config that cannot be read, or is empty, fails with the [include] lines given by set_included_from()
+    pub fn read_config(&mut self, config_filename: &Path, config: &mut PlayConfig,
+                       diagnostics: &mut Diagnostics) -> Result<(), PlayError> {
+        let config_lines = match self.config_lines(config_filename) {
+            Ok(config_lines) => config_lines,
+            Err(error) if self.included_from.is_empty() => return Err(error),
+            Err(error) => return Err(PlayError::Included { error: Box::new(error), chain: self.included_from.clone() }),
+        };
+
+        for (source_line, first_column, line) in &config_lines {
+            self.add_config(line, config_filename, (*source_line, *first_column), config, diagnostics);
+        }
         
-        if let Err(error_code) = grab_trimmed_file_lines(config_filename, &mut config_lines) {
-            return Err(error_code);
+        Ok(())
+    }
+
+    /// The lines of the config file, or of the inline [config] block, failing if there are none
+    fn config_lines(&self, config_filename: &Path) -> Result<FileLines, PlayError> {
+        let mut config_lines: FileLines = Vec::new();
+
+        match &self.inline_config {
+            Some((block_line, lines)) if lines.is_empty() => {
+                return Err(PlayError::EmptyInlineConfig { path: config_filename.to_path_buf(), line: *block_line });
+            }
+            Some((_, lines)) => config_lines.clone_from(lines),
+            None => grab_lines(self.bundle.as_deref(), config_filename, &mut config_lines)?,
         }
 
         if config_lines.is_empty() {
-            eprintln!("Error: Config file '{}' contains no lines", config_filename);
-            return Err(CONFIG_PARSING_ERROR);
+            return Err(PlayError::EmptyConfig { path: config_filename.to_path_buf() });
         }
-        
-        for line in &config_lines {
-            self.add_config(line, config);
-        }
-        
-        Ok(())
+        Ok(config_lines)
     }
 
     /// Main setup method that:
     /// - Reads the configuration file for this scene
     /// - Creates and prepares Player objects for each character
     /// - Sorts players by line number
-    pub fn prepare(&mut self, config_filename: &String) -> Result<(), u8> {
+    /// - Checks the merged line numbering for gaps and duplicates
+    pub fn prepare(&mut self, config_filename: &Path, diagnostics: &mut Diagnostics) -> Result<(), PlayError> {
         let mut config: PlayConfig = Vec::new();
+        self.config_filename = config_filename.to_path_buf();
         
-        if let Err(error_code) = self.read_config(config_filename, &mut config) {
-            return Err(error_code);
+        self.read_config(config_filename, &mut config, diagnostics)?;
+        self.process_config(&config, diagnostics)?;
+        self.sort_players(diagnostics);
+
+        Ok(())
+    }
+
+    /// Once every player is added, sorts the players by line number and checks the merged line
+    /// numbering for gaps and duplicates
+    pub(crate) fn sort_players(&mut self, diagnostics: &mut Diagnostics) {
+        self.players.sort();
+        self.check_line_numbers(diagnostics);
+    }
+
+    /// Walks the line numbers of all players in the order they will be recited:
+    /// - Tracks expected line numbers to detect missing/duplicate lines
+    /// - Records a diagnostic for each problem, located at the part file line that
+    ///   follows a gap or repeats a number
+    fn check_line_numbers(&self, diagnostics: &mut Diagnostics) {
+        // (line_number, part_path, source_line) in player order; the stable sort then
+        // breaks ties the same way recite() does
+        let mut locations: Vec<(usize, &Path, usize)> = Vec::new();
+        for player in &self.players {
+            let part_path = player.part_filename();
+            for (line_number, source_line) in player.line_locations() {
+                locations.push((line_number, part_path, source_line));
+            }
         }
-        
-        if let Err(error_code) = self.process_config(&config) {
-            return Err(error_code);
+        locations.sort_by_key(|(line_number, _, _)| *line_number);
+
+        let mut expected_line_number: usize = 0;
+        for (actual_line_number, part_path, source_line) in locations {
+            // Check for missing line numbers
+            if actual_line_number > expected_line_number {
+                for missing in expected_line_number..actual_line_number {
+                    diagnostics.warn(DiagnosticKind::MissingLineNumber, part_path, Some(source_line), None,
+                                     format!("Missing line number {}", missing));
+                }
+                expected_line_number = actual_line_number;
+            }
+
+            // Check for duplicate line numbers
+            if actual_line_number == expected_line_number {
+                // This is the expected line, advance the counter
+                expected_line_number += 1;
+            } else {
+                // This is a duplicate
+                diagnostics.warn(DiagnosticKind::DuplicateLineNumber, part_path, Some(source_line), None,
+                                 format!("Duplicate line number {}", actual_line_number));
+            }
         }
+    }
 
-        self.players.sort();
-        
-        Ok(())
+    /// The config file this fragment was (or, when loaded lazily, will be) prepared from
+    pub fn config_filename(&self) -> &Path {
+        &self.config_filename
+    }
+
+    /// Names the config file of a fragment that will be prepared later
+    pub fn set_config_filename(&mut self, config_filename: &Path) {
+        self.config_filename = config_filename.to_path_buf();
+    }
+
+    /// Makes the fragment read its config lines from a [config] block starting at `source_line` of
+    /// the script rather than from a config file; part files are then resolved relative to the script
+    pub fn set_inline_config(&mut self, source_line: usize, config_lines: FileLines) {
+        self.inline_config = Some((source_line, config_lines));
+    }
+
+    /// Where the fragment is named when its script was included: the script line naming the config,
+    /// then each [include] line around that script, innermost first
+    pub fn set_included_from(&mut self, included_from: Vec<(PathBuf, usize)>) {
+        self.included_from = included_from;
+    }
+
+    /// Makes prepare() read the config and part files from a bundle instead of the file system
+    pub fn set_bundle(&mut self, bundle: Option<Arc<Bundle>>) {
+        self.bundle = bundle;
+    }
+
+    /// Drops the players and their lines, e.g. once the fragment has been recited
+    pub fn unload(&mut self) {
+        self.players = Vec::new();
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// The (1-based) scene this fragment belongs to
+    pub fn scene(&self) -> usize {
+        self.scene
+    }
+
+    /// The fragment's players, sorted by their first line number
+    pub fn players(&self) -> &[Player] {
+        &self.players
     }
 
     pub fn has_title(&self) -> bool {
         !self.title.trim().is_empty()
     }
 
-    fn print_title(&self, is_first: bool) {
+    fn print_title(&self, is_first: bool, out: &mut dyn Write) -> io::Result<()> {
         if !self.title.trim().is_empty() {
             if !is_first {
-                println!(); // Blank line before scene title (except first)
+                writeln!(out)?; // Blank line before scene title (except first)
             }
-            println!("{}", self.title);
-            println!();
+            writeln!(out, "{}", self.title)?;
+            writeln!(out)?;
         }
+        Ok(())
     }
 
-    pub fn enter(&self, previous: &SceneFragment) {
-        self.print_title(false);
-        for player in &self.players {
-            // Check if player was in previous scene
-            let in_previous = previous.players.iter().any(|p| p.name() == player.name());
-            if !in_previous {
-                println!("[Enter {}.]", player.name());
+    /// Players on stage when the fragment starts, i.e. those without a mid-scene entrance
+    fn on_stage_at_start(&self) -> impl Iterator<Item = &Player> + '_ {
+        self.players.iter().filter(|player| player.entrance_line().is_none())
+    }
+
+    /// Players still on stage when the fragment ends, i.e. those without a mid-scene exit
+    fn on_stage_at_end(&self) -> impl Iterator<Item = &Player> + '_ {
+        self.players.iter().filter(|player| player.exit_line().is_none())
+    }
+
+    /// The players on stage when the fragment starts, in the order they enter
+    pub fn cast_at_start(&self) -> Cast {
+        self.on_stage_at_start().map(|player| (player.identity().clone(), player.name().clone())).collect()
+    }
+
+    /// The players still on stage when the fragment ends, in the order they entered
+    pub fn cast_at_end(&self) -> Cast {
+        self.on_stage_at_end().map(|player| (player.identity().clone(), player.name().clone())).collect()
+    }
+
+    /// Announces the players on stage at the start of this fragment who were not on stage
+    /// at the end of the previous one
+    pub fn enter(&self, previous: &SceneFragment, out: &mut dyn Write) -> io::Result<()> {
+        self.enter_after(&previous.cast_at_end(), out)
+    }
+
+    /// Same as enter(), given only the cast on stage at the end of the previous fragment
+    pub fn enter_after(&self, previous_cast: &[(String, String)], out: &mut dyn Write) -> io::Result<()> {
+        self.print_title(false, out)?;
+        for player in self.on_stage_at_start() {
+            // Check if the character was still on stage at the end of the previous scene, under any name
+            if !previous_cast.iter().any(|(identity, _)| identity == player.identity()) {
+                writeln!(out, "[Enter {}.]", player.name())?;
             }
         }
+        Ok(())
     }
 
-    pub fn enter_all(&self) {    
-        self.print_title(true);
-        for player in &self.players {
-            println!("[Enter {}.]", player.name());
+    pub fn enter_all(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.print_title(true, out)?;
+        for player in self.on_stage_at_start() {
+            writeln!(out, "[Enter {}.]", player.name())?;
         }
+        Ok(())
     }
 
-    pub fn exit(&self, next: &SceneFragment) {
-        for player in self.players.iter().rev() {
-            // Check if this player will be in next scene
-            let in_next = next.players.iter().any(|p| p.name() == player.name());
-            if !in_next {
-                println!("[Exit {}.]", player.name());
+    /// Announces the players on stage at the end of this fragment who will not be on stage
+    /// at the start of the next one
+    pub fn exit(&self, next: &SceneFragment, out: &mut dyn Write) -> io::Result<()> {
+        Self::exit_cast(&self.cast_at_end(), &next.cast_at_start(), out)
+    }
+
+    pub fn exit_all(&self, out: &mut dyn Write) -> io::Result<()> {
+        Self::exit_cast(&self.cast_at_end(), &[], out)
+    }
+
+    /// Announces in reverse order the players of `cast`, the cast on stage at the end of a fragment,
+    /// who are not in `next_cast`, the cast on stage at the start of the next one; players are the
+    /// same character if their identities match, whatever name they are printed under
+    pub fn exit_cast(cast: &[(String, String)], next_cast: &[(String, String)], out: &mut dyn Write) -> io::Result<()> {
+        for (identity, name) in cast.iter().rev() {
+            // Check if this character will be on stage at the start of the next scene
+            if !next_cast.iter().any(|(next_identity, _)| next_identity == identity) {
+                writeln!(out, "[Exit {}.]", name)?;
             }
         }
+        Ok(())
     }
 
-    pub fn exit_all(&self) {
-        for player in self.players.iter().rev() {
-            println!("[Exit {}.]", player.name());
+    /// Pending mid-scene (entrance, exit) cues as (line_number, player_index), in line order
+    fn pending_cues(&self) -> (CueLines, CueLines) {
+        let mut entrances: CueLines = self.players.iter().enumerate()
+            .filter_map(|(index, player)| player.entrance_line().map(|line_number| (line_number, index)))
+            .collect();
+        let mut exits: CueLines = self.players.iter().enumerate()
+            .filter_map(|(index, player)| player.exit_line().map(|line_number| (line_number, index)))
+            .collect();
+        entrances.sort();
+        exits.sort();
+        (entrances, exits)
+    }
+
+    /// Every player with lines left, queued by its next line number
+    fn line_queue(&self) -> LineQueue {
+        self.players.iter().enumerate()
+            .filter_map(|(index, player)| player.next_line().map(|line_number| Reverse((line_number, index))))
+            .collect()
+    }
+
+    /// Prints "[Enter X.]"/"[Exit X.]" for every pending mid-scene cue that is due, as one block
+    /// set apart from the dialogue, and removes them from the pending (line_number, player_index) cues
+    fn announce(names: &[String], action: &str, cues: &mut CueLines, due: impl Fn(usize) -> bool,
+                current_speaker: &mut String, out: &mut dyn Write) -> io::Result<()> {
+        let (now, later): (CueLines, CueLines) = cues.iter().partition(|(line_number, _)| due(*line_number));
+        if now.is_empty() {
+            return Ok(());
+        }
+        *cues = later;
+
+        // Whoever speaks next is named again after the announcement
+        current_speaker.clear();
+        writeln!(out)?;
+        for (_, player_index) in now {
+            writeln!(out, "[{} {}.]", action, names[player_index])?;
         }
+        Ok(())
     }
 
-    /// Orchestrates dialogue delivery:
-    /// - Repeatedly finds the player with the smallest next line number
+    /// Orchestrates dialogue delivery as a k-way merge of the players' sorted lines:
+    /// - Repeatedly takes the player with the smallest next line number from a priority queue
+    ///   (ties go to the player earliest in the fragment: players are sorted by their first line,
+    ///   so the one with the earliest first line, or among those sharing it the one listed first)
+    /// - Announces mid-scene exits cued before that line, then mid-scene entrances cued at or before it
     /// - That player speaks their line
-    /// - Tracks expected line numbers to detect missing/duplicate lines
-    /// -  Warns about line number issues in whinge mode
-    /// - Continues until all players have delivered all lines
-    pub fn recite(&mut self) {
+    /// - Continues until all players have delivered all lines, then announces any remaining cues
+    pub fn recite(&mut self, out: &mut dyn Write) -> io::Result<()> {
         let mut current_speaker = String::new();
-        let mut expected_line_number: usize = 0;
-        
-        loop {
-            // Find the player with the smallest next line number
-            let mut next_line_number: Option<usize> = None;
-            let mut next_player_index: Option<usize> = None;
-            for (index, player) in self.players.iter().enumerate() {
-                if let Some(line_num) = player.next_line() {
-                    // next_line_number is_none() means we haven't found any player with a line yet in this iteration
-                    // this is the first player we've encountered who has lines remaining, 
-                    // which by default is the next line number.
-                    if next_line_number.is_none() || line_num < next_line_number.unwrap() {
-                        next_line_number = Some(line_num);
-                        next_player_index = Some(index);
-                    }
-                }
-            }
-            // If no player has lines left, we're done
-            if next_player_index.is_none() {
-                break;
+        let names: Vec<String> = self.players.iter().map(|player| player.name().clone()).collect();
+        let (mut entrances, mut exits) = self.pending_cues();
+        let mut queue = self.line_queue();
+
+        while let Some(Reverse((line_number, player_index))) = queue.pop() {
+            Self::announce(&names, "Exit", &mut exits, |exit| exit < line_number, &mut current_speaker, out)?;
+            Self::announce(&names, "Enter", &mut entrances, |entrance| entrance <= line_number,
+                           &mut current_speaker, out)?;
+            self.players[player_index].speak(&mut current_speaker, out)?;
+            if let Some(next_line_number) = self.players[player_index].next_line() {
+                queue.push(Reverse((next_line_number, player_index)));
             }
-            
-            // Check for missing line numbers
-            let actual_line_number = next_line_number.unwrap();
-            if actual_line_number > expected_line_number {
-                if WHINGE_MODE.load(Ordering::SeqCst) {
-                    for missing in expected_line_number..actual_line_number {
-                        eprintln!("Warning: Missing line number {}", missing);
+        }
+
+        // No player has lines left
+        Self::announce(&names, "Enter", &mut entrances, |_| true, &mut current_speaker, out)?;
+        Self::announce(&names, "Exit", &mut exits, |_| true, &mut current_speaker, out)?;
+        Ok(())
+    }
+
+    /// Recites exactly as recite() does, but with every player on a thread of its own:
+    /// - The fragment keeps the running order and cues each player over that player's channel,
+    ///   sending the name of the current speaker
+    /// - The player delivers its line into a buffer and hands it back with the new current speaker
+    ///   and its next line number, and the fragment writes it out and announces any due cues
+    /// - A player thread that fails (or panics) is reported as an error naming the player and line;
+    ///   the fragment then stops cueing, which lets every other player thread finish
+    pub fn recite_concurrently(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        let mut current_speaker = String::new();
+        let names: Vec<String> = self.players.iter().map(|player| player.name().clone()).collect();
+        let (mut entrances, mut exits) = self.pending_cues();
+        let mut queue = self.line_queue();
+
+        thread::scope(|scope| {
+            let mut cue_senders: Vec<Sender<String>> = Vec::new();
+            let mut delivery_receivers: Vec<Receiver<Delivery>> = Vec::new();
+            let mut handles = Vec::new();
+            for player in self.players.iter_mut() {
+                let (cue_sender, cues) = mpsc::channel::<String>();
+                let (deliveries, delivery_receiver) = mpsc::channel::<Delivery>();
+                handles.push(scope.spawn(move || {
+                    // Deliver one line per cue until the fragment stops cueing
+                    for mut speaker in cues {
+                        let mut text: Vec<u8> = Vec::new();
+                        let delivery = player.speak(&mut speaker, &mut text).map(|_| (text, speaker, player.next_line()));
+                        if deliveries.send(delivery).is_err() {
+                            return;
+                        }
                     }
-                }
-                expected_line_number = actual_line_number;
+                }));
+                cue_senders.push(cue_sender);
+                delivery_receivers.push(delivery_receiver);
             }
-            
-            // Check for duplicate line numbers
-            if actual_line_number == expected_line_number {
-                // This is the expected line, advance the counter
-                expected_line_number += 1;
-            } else if actual_line_number < expected_line_number {
-                // This is a duplicate
-                if WHINGE_MODE.load(Ordering::SeqCst) {
-                    eprintln!("Warning: Duplicate line number {}", actual_line_number);
+
+            let mut conduct = || -> io::Result<()> {
+                while let Some(Reverse((line_number, player_index))) = queue.pop() {
+                    Self::announce(&names, "Exit", &mut exits, |exit| exit < line_number, &mut current_speaker, out)?;
+                    Self::announce(&names, "Enter", &mut entrances, |entrance| entrance <= line_number,
+                                   &mut current_speaker, out)?;
+
+                    let failed = || io::Error::other(format!("{} failed to deliver line {}", names[player_index], line_number));
+                    cue_senders[player_index].send(current_speaker.clone()).map_err(|_| failed())?;
+                    let (text, speaker, next_line) = delivery_receivers[player_index].recv().map_err(|_| failed())??;
+                    out.write_all(&text)?;
+                    current_speaker = speaker;
+                    if let Some(next_line_number) = next_line {
+                        queue.push(Reverse((next_line_number, player_index)));
+                    }
                 }
+
+                // No player has lines left
+                Self::announce(&names, "Enter", &mut entrances, |_| true, &mut current_speaker, out)?;
+                Self::announce(&names, "Exit", &mut exits, |_| true, &mut current_speaker, out)
+            };
+            let result = conduct();
+
+            // Closing the cue channels ends every player thread; a panic has already been reported above
+            drop(cue_senders);
+            for handle in handles {
+                let _ = handle.join();
             }
-            
-            // Have the selected player speak their line
-            let player_index = next_player_index.unwrap();
-            self.players[player_index].speak(&mut current_speaker);
-        }
+            result
+        })
     }
-}
\ No newline at end of file
+}
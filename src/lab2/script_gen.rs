@@ -3,31 +3,83 @@
 use std::fs::File;
 use std::io::BufReader;
 use std::io::BufRead;
+use std::path::{Component, Path, PathBuf};
 
-use super::declarations::{FAILED_TO_OPEN_FILE, FAILED_TO_READ_LINE_FROM_FILE};
+use super::play_error::PlayError;
 
-pub fn grab_trimmed_file_lines(filename: &String, lines: &mut Vec<String>) -> Result<(), u8> {
+pub type FileLines = Vec<(usize, usize, String)>; // (source_line, first_column, trimmed_text)
+
+// A line whose first non-blank character is this is a comment, in script, config and part files alike
+pub const COMMENT_MARKER: char = '#';
+
+/// Whether a trimmed line is a comment
+pub fn is_comment(text: &str) -> bool {
+    text.starts_with(COMMENT_MARKER)
+}
+
+/// 1-based column (counted in characters) at which `token` starts, where `token` is a
+/// slice of `line` and the first character of `line` sits at `first_column`
+pub fn column_of(line: &str, token: &str, first_column: usize) -> usize {
+    let offset = (token.as_ptr() as usize).saturating_sub(line.as_ptr() as usize).min(line.len());
+    first_column + line[..offset].chars().count()
+}
+
+/// Path of a file named inside `referencing_file`: relative names are resolved against the
+/// directory containing `referencing_file`, absolute names are used as they are
+pub fn resolve_relative(referencing_file: &Path, filename: &str) -> PathBuf {
+    match referencing_file.parent() {
+        Some(directory) => directory.join(filename),
+        None => PathBuf::from(filename),
+    }
+}
+
+/// `path` with "." components dropped and each ".." removing the component before it, without
+/// looking at the file system; leading ".." components that have nothing to remove are kept
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+pub fn grab_trimmed_file_lines(filename: &Path, lines: &mut FileLines) -> Result<(), PlayError> {
     // The core function used for extracting data from files
     // Used for both reading the config file line by line and reading the parts file line by line
     let file = match File::open(filename) {
         Ok(f) => f,
-        Err(error_code) => {
-            eprintln!("Error: Failed to open file '{}': {}", filename, error_code);
-            return Err(FAILED_TO_OPEN_FILE);
+        Err(source) => {
+            return Err(PlayError::FailedToOpenFile { path: filename.to_path_buf(), source });
         }
     };
     
-    let mut reader = BufReader::new(file);
+    grab_trimmed_lines(BufReader::new(file), filename, lines)
+}
+
+/// Reads already opened lines, e.g. a file held in a bundle, the way grab_trimmed_file_lines() reads a file:
+/// - Each line keeps its 1-based line and starting column in `filename` so that problems can be located
+/// - Comment lines are left out entirely, so they never reach the parsers (nor end a speech as a blank line would)
+pub fn grab_trimmed_lines(mut reader: impl BufRead, filename: &Path, lines: &mut FileLines) -> Result<(), PlayError> {
     let mut line = String::new();
+    let mut source_line: usize = 0;
     
     loop {
         line.clear();
         
         let bytes_read = match reader.read_line(&mut line) {
             Ok(bytes) => bytes,
-            Err(error_code) => {
-                eprintln!("Error: Failed to read line from file '{}': {}", filename, error_code);
-                return Err(FAILED_TO_READ_LINE_FROM_FILE);
+            Err(source) => {
+                return Err(PlayError::FailedToReadLine {
+                    path: filename.to_path_buf(),
+                    line: source_line + 1,
+                    source,
+                });
             }
         };
         
@@ -35,9 +87,12 @@ pub fn grab_trimmed_file_lines(filename: &String, lines: &mut Vec<String>) -> Re
             return Ok(());
         }
 
-        lines.push(line.trim().to_string());
+        source_line += 1;
+        if is_comment(line.trim()) {
+            continue;
+        }
+
+        let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+        lines.push((source_line, indent + 1, line.trim().to_string()));
     }
 }
-
-
-    
\ No newline at end of file
@@ -0,0 +1,35 @@
+/// sink.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Output sinks for recitation. A play can be recited into any std::io::Write;
+/// Tee forwards everything written to it to two writers, and can be nested to reach more.
+use std::io::{self, Write};
+
+pub struct Tee<A: Write, B: Write> {
+    first: A,
+    second: B,
+}
+
+impl<A: Write, B: Write> Tee<A, B> {
+    pub fn new(first: A, second: B) -> Tee<A, B> {
+        Tee { first, second }
+    }
+
+    /// Gives back the two wrapped writers
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<A: Write, B: Write> Write for Tee<A, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Both writers must receive the whole buffer, otherwise they would drift apart
+        self.first.write_all(buf)?;
+        self.second.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.first.flush()?;
+        self.second.flush()
+    }
+}
@@ -0,0 +1,676 @@
+/// split.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: The inverse of reciting a play. Reads a rendered script (scene titles, "[Enter X.]"
+/// and "[Exit X.]" announcements, "Name." headers, dialogue and stage directions) and writes the
+/// script, scene config and part files that Play::prepare reads. The written files are loaded and
+/// recited again, and the split only succeeds if that reproduces the rendered script exactly.
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use super::play::Play;
+use super::play_error::PlayError;
+use super::player::{is_stage_direction, CONTINUATION_MARKER};
+use super::scene_fragment::{ENTER_OPTION, EXIT_OPTION, NAME_OPTION, OPTION_SEPARATOR};
+use super::tokenizer::quote_token;
+
+const ENTER_ANNOUNCEMENT: &str = "[Enter ";
+const EXIT_ANNOUNCEMENT: &str = "[Exit ";
+const ANNOUNCEMENT_END: &str = ".]";
+const SPEAKER_END: char = '.';
+const SCENE_MARKER: &str = "[scene]";
+
+// Blank lines before a paragraph: one separates paragraphs, two mark the end of a fragment
+const PARAGRAPH_BREAK: usize = 1;
+const FRAGMENT_BREAK: usize = 2;
+
+type Paragraph<'a> = (usize, usize, Vec<&'a str>); // (blank_lines_before, first_source_line, lines)
+type Announcement = (bool, String); // (is_entrance, player_name)
+type OnStage = Vec<(String, String)>; // (part_name, printed_name) of each player on stage
+
+/// What a paragraph of the rendered script says
+enum Block {
+    Title(String),
+    Announcements(Vec<Announcement>),
+    Direction(String),
+    Speech(String, Vec<String>),
+}
+
+/// A player of one fragment being rebuilt from the rendered script; `display` is the name printed
+/// when it differs from the part name, which a character keeps from the fragment it first entered in
+#[derive(Clone)]
+struct SplitPart {
+    name: String,
+    display: Option<String>,
+    lines: Vec<(usize, String)>,
+    entrance: Option<usize>,
+    exit: Option<usize>,
+}
+
+impl SplitPart {
+    fn new(name: &str, printed: &str) -> SplitPart {
+        SplitPart {
+            name: name.to_string(),
+            display: (name != printed).then(|| printed.to_string()),
+            lines: Vec::new(),
+            entrance: None,
+            exit: None,
+        }
+    }
+
+    /// The name the rendered script prints for the player
+    fn printed(&self) -> &str {
+        self.display.as_deref().unwrap_or(&self.name)
+    }
+}
+
+type CueBlock = (usize, Vec<Announcement>); // (next_line, announcements)
+
+/// A fragment being rebuilt from the rendered script; an empty title continues the current scene.
+/// Besides the lines, it keeps the announcements read for it, which reciting it must reproduce:
+/// the players entering with it, its mid-scene blocks and the players leaving with it
+#[derive(Clone)]
+struct SplitFragment {
+    title: String,
+    parts: Vec<SplitPart>,
+    next_line: usize,
+    start: usize,
+    entered: Vec<String>,
+    cues: Vec<CueBlock>,
+    exited: Vec<String>,
+}
+
+impl SplitFragment {
+    /// A fragment starting at paragraph `start` with the players in `on_stage`
+    fn new(title: &str, on_stage: &OnStage, start: usize) -> SplitFragment {
+        SplitFragment {
+            title: title.to_string(),
+            parts: on_stage.iter().map(|(name, printed)| SplitPart::new(name, printed)).collect(),
+            next_line: 0,
+            start,
+            entered: Vec::new(),
+            cues: Vec::new(),
+            exited: Vec::new(),
+        }
+    }
+
+    fn part(&mut self, name: &str) -> &mut SplitPart {
+        let index = match self.parts.iter().position(|part| part.printed() == name) {
+            Some(index) => index,
+            None => {
+                self.parts.push(SplitPart::new(name, name));
+                self.parts.len() - 1
+            }
+        };
+        &mut self.parts[index]
+    }
+
+    fn add_line(&mut self, name: &str, text: String) {
+        let line_number = self.next_line;
+        self.next_line += 1;
+        self.part(name).lines.push((line_number, text));
+    }
+
+    /// Players entering with the fragment, which must all be entrances
+    fn enter_with(&mut self, announcements: &[Announcement]) -> Result<(), String> {
+        for (is_entrance, name) in announcements {
+            if !is_entrance {
+                return Err(format!("{} exits before the scene begins", name));
+            }
+            self.part(name);
+            self.entered.push(name.clone());
+        }
+        Ok(())
+    }
+
+    /// Players leaving with the fragment, which must all be exits
+    fn leave_with(&mut self, announcements: &[Announcement]) -> Result<(), String> {
+        for (is_entrance, name) in announcements {
+            if *is_entrance {
+                return Err(format!("{} enters after the scene ends", name));
+            }
+            self.exited.push(name.clone());
+        }
+        Ok(())
+    }
+
+    /// Entrances and exits partway through the fragment, cued at the surrounding line numbers
+    fn cue(&mut self, announcements: &[Announcement]) -> Result<(), String> {
+        let next_line = self.next_line;
+        for (is_entrance, name) in announcements {
+            let part = self.part(name);
+            if *is_entrance {
+                // A player who left cannot come back within the same fragment
+                if part.entrance.is_some() || part.exit.is_some() {
+                    return Err(format!("{} enters twice in one scene", name));
+                }
+                part.entrance = Some(next_line);
+            } else {
+                if part.exit.is_some() || next_line == 0 {
+                    return Err(format!("cannot place the exit of {} here", name));
+                }
+                part.exit = Some(next_line - 1);
+            }
+        }
+        self.cues.push((next_line, announcements.to_vec()));
+        Ok(())
+    }
+
+    /// Whether reciting the fragment would print its mid-scene blocks as they were read: each block
+    /// holds one kind of announcement, and between two lines exits come before entrances (after the
+    /// last line, entrances come first)
+    fn cues_in_place(&self) -> bool {
+        self.cues.iter().enumerate().all(|(index, (next_line, announcements))| {
+            let is_entrance = announcements[0].0;
+            let one_kind = announcements.iter().all(|(entrance, _)| *entrance == is_entrance);
+            let previous = index.checked_sub(1).map(|previous| &self.cues[previous])
+                .filter(|(previous_line, _)| previous_line == next_line);
+            one_kind && match previous {
+                Some((_, previous_announcements)) if *next_line < self.next_line => !previous_announcements[0].0 && is_entrance,
+                Some((_, previous_announcements)) => previous_announcements[0].0 && !is_entrance,
+                None => true,
+            }
+        })
+    }
+
+    /// The order to list the parts in so that reciting the fragment announces its players in the
+    /// order they were read, or None if no order does:
+    /// - Players are recited by their first line, with silent players first in the order listed
+    /// - Entrances, and each mid-scene block, are announced in that order; exits at the end in reverse
+    /// - Only silent players can be reordered, so they keep their places unless an announcement says otherwise
+    fn part_order(&self) -> Option<Vec<usize>> {
+        let position = |name: &String| self.parts.iter().position(|part| part.printed() == name);
+        let mut sequences: Vec<Vec<&String>> = vec![self.entered.iter().collect(), self.exited.iter().rev().collect()];
+        for (_, announcements) in &self.cues {
+            sequences.push(announcements.iter().map(|(_, name)| name).collect());
+        }
+
+        // (earlier, later) silent parts that must be listed in this order
+        let mut before: Vec<(usize, usize)> = Vec::new();
+        for sequence in &sequences {
+            for pair in sequence.windows(2) {
+                let (earlier, later) = (position(pair[0])?, position(pair[1])?);
+                match (self.parts[earlier].lines.first(), self.parts[later].lines.first()) {
+                    (Some((first, _)), Some((second, _))) if first >= second => return None,
+                    (Some(_), None) => return None,
+                    (None, None) => before.push((earlier, later)),
+                    _ => {}
+                }
+            }
+        }
+
+        let silent: Vec<usize> = (0..self.parts.len()).filter(|index| self.parts[*index].lines.is_empty()).collect();
+        let mut remaining = silent.clone();
+        let mut order: Vec<usize> = (0..self.parts.len()).collect();
+        for slot in silent {
+            let next = remaining.iter().position(|part| {
+                !before.iter().any(|(earlier, later)| later == part && remaining.contains(earlier))
+            })?;
+            order[slot] = remaining.remove(next);
+        }
+        Some(order)
+    }
+
+    /// Whether reciting the fragment would announce its players as the rendered script does
+    fn reproducible(&self) -> bool {
+        self.cues_in_place() && self.part_order().is_some()
+    }
+}
+
+/// One way of reading the rendered script so far, since a block of only exits or only entrances
+/// may be mid-scene cues or the end of a fragment
+#[derive(Clone)]
+struct Reading {
+    fragments: Vec<SplitFragment>,
+    on_stage: OnStage,
+    cost: (usize, usize), // (unreproducible_fragments, fragments)
+}
+
+impl Reading {
+    fn fragment(&mut self) -> &mut SplitFragment {
+        self.fragments.last_mut().unwrap()
+    }
+
+    /// Paragraph the current fragment starts at; readings that agree on it read the rest of the script alike
+    fn start(&self) -> Option<usize> {
+        self.fragments.last().map(|fragment| fragment.start)
+    }
+
+    /// The cost once the current fragment is ended, and optionally another one begun
+    fn cost_after(&self, begin: bool) -> (usize, usize) {
+        let unreproducible = self.fragments.last().is_some_and(|fragment| !fragment.reproducible());
+        (self.cost.0 + unreproducible as usize, self.cost.1 + begin as usize)
+    }
+
+    /// Ends the current fragment and begins `next`
+    fn begin(&mut self, next: SplitFragment) {
+        self.cost = self.cost_after(true);
+        self.fragments.push(next);
+    }
+
+    /// Ends the current fragment with the exits of `announcements` and begins an untitled one at
+    /// paragraph `start` with its entrances
+    fn split_at(&mut self, announcements: &[Announcement], start: usize) {
+        let staying: OnStage = self.on_stage.iter()
+            .filter(|(_, printed)| !announcements.iter().any(|(is_entrance, name)| !is_entrance && name == printed))
+            .cloned()
+            .collect();
+        for (is_entrance, name) in announcements {
+            if !is_entrance {
+                self.fragment().exited.push(name.clone());
+            }
+        }
+        let mut next = SplitFragment::new("", &staying, start);
+        for (is_entrance, name) in announcements {
+            if *is_entrance {
+                next.part(name);
+                next.entered.push(name.clone());
+            }
+        }
+        self.begin(next);
+    }
+
+    /// Follows `announcements` in who is on stage
+    fn announce(&mut self, announcements: &[Announcement]) {
+        for (is_entrance, name) in announcements {
+            if *is_entrance {
+                self.on_stage.push((name.clone(), name.clone()));
+            } else {
+                self.on_stage.retain(|(_, printed)| printed != name);
+            }
+        }
+    }
+
+    /// Takes a name nobody in the current fragment is printed as to be a player carried over from the
+    /// previous fragment under another display name, since reciting announces everyone else: the first
+    /// carried player not yet printed in the fragment is renamed, here and on stage
+    fn recognize(&mut self, name: &str) {
+        let fragment = self.fragments.last_mut().unwrap();
+        if fragment.parts.iter().any(|part| part.printed() == name) {
+            return;
+        }
+        let Some(index) = fragment.parts.iter().position(|part| {
+            part.lines.is_empty() && part.entrance.is_none() && part.exit.is_none()
+                && !fragment.entered.iter().any(|entered| entered == part.printed())
+        }) else {
+            return;
+        };
+        let part = &mut fragment.parts[index];
+        for (_, printed) in self.on_stage.iter_mut().filter(|(_, printed)| printed == part.printed()) {
+            *printed = name.to_string();
+        }
+        part.display = (part.name != name).then(|| name.to_string());
+    }
+
+    /// Adds a line spoken by, or a direction given to, the player printed as `name`
+    fn add_line(&mut self, name: &str, text: String) {
+        self.recognize(name);
+        self.fragment().add_line(name, text);
+    }
+}
+
+/// Applies `step` to every reading, dropping the readings it fails for; fails with the first
+/// message if it fails for all of them
+fn advance(readings: Vec<Reading>, mut step: impl FnMut(&mut Reading) -> Result<(), String>)
+           -> Result<Vec<Reading>, String> {
+    let mut first_failure: Option<String> = None;
+    let mut kept: Vec<Reading> = Vec::new();
+    for mut reading in readings {
+        match step(&mut reading) {
+            Ok(()) => kept.push(reading),
+            Err(message) => {
+                first_failure.get_or_insert(message);
+            }
+        }
+    }
+    match first_failure {
+        Some(message) if kept.is_empty() => Err(message),
+        _ => Ok(kept),
+    }
+}
+
+/// Keeps the cheapest of the readings whose current fragment starts at the same paragraph
+/// (the earliest one on a tie)
+fn merge(readings: Vec<Reading>) -> Vec<Reading> {
+    let mut merged: Vec<Reading> = Vec::new();
+    for reading in readings {
+        match merged.iter_mut().find(|kept| kept.start() == reading.start()) {
+            Some(kept) => {
+                if reading.cost < kept.cost {
+                    *kept = reading;
+                }
+            }
+            None => merged.push(reading),
+        }
+    }
+    merged
+}
+
+fn parse_announcement(line: &str) -> Option<Announcement> {
+    let (is_entrance, rest) = match (line.strip_prefix(ENTER_ANNOUNCEMENT), line.strip_prefix(EXIT_ANNOUNCEMENT)) {
+        (Some(rest), _) => (true, rest),
+        (None, Some(rest)) => (false, rest),
+        (None, None) => return None,
+    };
+    rest.strip_suffix(ANNOUNCEMENT_END).map(|name| (is_entrance, name.to_string()))
+}
+
+/// Works out what a paragraph is from its lines:
+/// - Bracketed lines that all announce entrances/exits are announcements, other bracketed text is a direction
+/// - "Name." followed by dialogue is a speech
+/// - Any other single line is a scene title
+fn classify(lines: &[&str]) -> Option<Block> {
+    let announcements: Option<Vec<Announcement>> = lines.iter().map(|line| parse_announcement(line)).collect();
+    if let Some(announcements) = announcements {
+        return Some(Block::Announcements(announcements));
+    }
+
+    let text = lines.join("\n");
+    if is_stage_direction(&text) {
+        return Some(Block::Direction(text));
+    }
+
+    match lines {
+        [title] => Some(Block::Title(title.to_string())),
+        [header, dialogue @ ..] => header.strip_suffix(SPEAKER_END)
+            .filter(|name| !name.is_empty())
+            .map(|name| Block::Speech(name.to_string(), dialogue.iter().map(|line| line.to_string()).collect())),
+        [] => None,
+    }
+}
+
+/// Splits the rendered text into paragraphs, and counts the blank lines at its end
+fn paragraphs(rendered: &str) -> (Vec<Paragraph<'_>>, usize) {
+    let mut paragraphs: Vec<Paragraph> = Vec::new();
+    let mut blank_lines: usize = 0;
+    let mut current: Option<Paragraph> = None;
+
+    for (index, line) in rendered.lines().enumerate() {
+        if line.is_empty() {
+            if let Some(paragraph) = current.take() {
+                paragraphs.push(paragraph);
+            }
+            blank_lines += 1;
+        } else {
+            match current.as_mut() {
+                Some((_, _, lines)) => lines.push(line),
+                None => {
+                    current = Some((blank_lines, index + 1, vec![line]));
+                    blank_lines = 0;
+                }
+            }
+        }
+    }
+
+    if let Some(paragraph) = current.take() {
+        paragraphs.push(paragraph);
+    }
+    (paragraphs, blank_lines)
+}
+
+/// Rebuilds fragments from a rendered script, following who is on stage:
+/// - A title starts a new scene; the announcements right after it are the players entering with it
+/// - Exits right before the next title (or the end of the script) are players leaving with the fragment
+/// - A block of exits followed by entrances, or anything after two blank lines, starts an untitled fragment
+/// - A block of only exits or only entrances may either start an untitled fragment or be mid-scene cues;
+///   both readings are followed, and the one kept has the fewest fragments whose announcements reciting
+///   would not reproduce, then the fewest fragments
+/// - Any other announcement is a mid-scene entrance or exit cued at the surrounding line numbers
+/// - A speaker, or an exit, under a name no one in the fragment has is a player carried over from the
+///   previous fragment under a new display name (see Reading::recognize)
+/// - Stage directions go to the part of the player who spoke last (or speaks next)
+fn rebuild(path: &Path, rendered: &str) -> Result<Vec<SplitFragment>, PlayError> {
+    let error = |line: usize, message: String| PlayError::UnsplittableScript { path: path.to_path_buf(), line, message };
+
+    let (paragraphs, trailing_blank_lines) = paragraphs(rendered);
+    let mut readings: Vec<Reading> = vec![Reading { fragments: Vec::new(), on_stage: Vec::new(), cost: (0, 0) }];
+    let mut last_speaker: Option<String> = None;
+    let mut after_title = false;
+
+    for (index, (blank_lines, source_line, lines)) in paragraphs.iter().enumerate() {
+        let block = classify(lines).ok_or_else(|| error(*source_line, "not a title, announcement or speech".to_string()))?;
+        let just_titled = after_title;
+        after_title = false;
+
+        if readings[0].fragments.is_empty() && !matches!(block, Block::Title(_)) {
+            return Err(error(*source_line, "a rendered script must start with a scene title".to_string()));
+        }
+
+        // Anything after two blank lines means the previous fragment ended without any exits or entrances
+        let starts_untitled = !matches!(block, Block::Title(_)) && *blank_lines >= FRAGMENT_BREAK && !just_titled;
+        if starts_untitled {
+            readings = advance(readings, |reading| {
+                if reading.fragment().next_line == 0 {
+                    return Err("a fragment with no lines cannot end here".to_string());
+                }
+                let next = SplitFragment::new("", &reading.on_stage, index);
+                reading.begin(next);
+                Ok(())
+            }).map_err(|message| error(*source_line, message))?;
+            last_speaker = None;
+        }
+
+        match block {
+            Block::Title(title) => {
+                for reading in readings.iter_mut() {
+                    let next = SplitFragment::new(&title, &reading.on_stage, index);
+                    reading.begin(next);
+                }
+                last_speaker = None;
+                after_title = true;
+            }
+            Block::Announcements(announcements) => {
+                let before_title = match paragraphs.get(index + 1) {
+                    Some((next_blank_lines, _, next_lines)) => {
+                        *next_blank_lines == PARAGRAPH_BREAK && matches!(classify(next_lines), Some(Block::Title(_)))
+                    }
+                    None => trailing_blank_lines == 0,
+                };
+                let entrances_follow_exits = announcements.iter().skip_while(|(is_entrance, _)| !is_entrance)
+                    .all(|(is_entrance, _)| *is_entrance);
+                let mixed = announcements.iter().any(|(is_entrance, _)| *is_entrance)
+                    && announcements.iter().any(|(is_entrance, _)| !is_entrance);
+                for reading in readings.iter_mut() {
+                    for (_, name) in announcements.iter().filter(|(is_entrance, _)| !is_entrance) {
+                        reading.recognize(name);
+                    }
+                }
+
+                readings = if just_titled && *blank_lines == PARAGRAPH_BREAK {
+                    // Players entering with the fragment
+                    advance(readings, |reading| reading.fragment().enter_with(&announcements))
+                } else if before_title {
+                    // Players leaving with the fragment
+                    advance(readings, |reading| reading.fragment().leave_with(&announcements))
+                } else if mixed && entrances_follow_exits && !starts_untitled {
+                    // One fragment ends and an untitled one begins
+                    advance(readings, |reading| {
+                        if reading.fragment().next_line == 0 {
+                            return reading.fragment().cue(&announcements);
+                        }
+                        reading.split_at(&announcements, index);
+                        Ok(())
+                    })
+                } else if !mixed && !starts_untitled {
+                    // Either way; the untitled fragment begins after the cheapest reading so far
+                    let ending = readings.iter()
+                        .filter(|reading| reading.fragments.last().is_some_and(|fragment| fragment.next_line > 0))
+                        .map(|reading| {
+                            let mut ending = reading.clone();
+                            ending.split_at(&announcements, index);
+                            ending
+                        })
+                        .min_by_key(|ending| ending.cost);
+                    match (advance(readings, |reading| reading.fragment().cue(&announcements)), ending) {
+                        (Ok(readings), Some(ending)) => Ok(readings.into_iter().chain([ending]).collect()),
+                        (Err(_), Some(ending)) => Ok(vec![ending]),
+                        (readings, None) => readings,
+                    }
+                } else {
+                    // Entrances and exits partway through the fragment
+                    advance(readings, |reading| reading.fragment().cue(&announcements))
+                }.map_err(|message| error(*source_line, message))?;
+
+                for reading in readings.iter_mut() {
+                    reading.announce(&announcements);
+                }
+                last_speaker = None;
+            }
+            Block::Direction(text) => {
+                let owner = last_speaker.clone()
+                    .or_else(|| paragraphs[index + 1..].iter().find_map(|(_, _, next_lines)| match classify(next_lines) {
+                        Some(Block::Speech(name, _)) => Some(name),
+                        _ => None,
+                    }))
+                    .or_else(|| readings[0].on_stage.first().map(|(_, printed)| printed.clone()))
+                    .ok_or_else(|| error(*source_line, "no player to give this stage direction to".to_string()))?;
+                for reading in readings.iter_mut() {
+                    reading.add_line(&owner, text.clone());
+                }
+                last_speaker = None;
+            }
+            Block::Speech(name, dialogue) => {
+                for reading in readings.iter_mut() {
+                    for text in &dialogue {
+                        reading.add_line(&name, text.clone());
+                    }
+                }
+                last_speaker = Some(name);
+            }
+        }
+        readings = merge(readings);
+    }
+
+    let reading = readings.into_iter().min_by_key(|reading| reading.cost_after(false)).unwrap();
+    if reading.fragments.is_empty() {
+        return Err(error(1, "the rendered script is empty".to_string()));
+    }
+
+    // List silent players in the order their announcements need
+    let mut fragments = reading.fragments;
+    for fragment in fragments.iter_mut() {
+        if let Some(order) = fragment.part_order() {
+            fragment.parts = order.iter().map(|index| fragment.parts[*index].clone()).collect();
+        }
+    }
+    Ok(fragments)
+}
+
+/// A filename-safe version of a player name
+fn file_stem(name: &str) -> String {
+    name.chars().map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '_' }).collect()
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), PlayError> {
+    match fs::write(path, contents) {
+        Ok(()) => Ok(()),
+        Err(source) => Err(PlayError::FailedToOpenFile { path: path.to_path_buf(), source }),
+    }
+}
+
+/// A part file: one numbered line per line of dialogue, with continuation lines for multi-line text
+fn part_text(part: &SplitPart) -> String {
+    let mut text = String::new();
+    for (line_number, line) in &part.lines {
+        let mut line_parts = line.split('\n');
+        text.push_str(&format!("{} {}\n", line_number, line_parts.next().unwrap_or("")));
+        for continuation in line_parts {
+            text.push_str(&format!("{} {}\n", CONTINUATION_MARKER, continuation));
+        }
+    }
+    text
+}
+
+/// A config line naming the player, their part file, any mid-scene cues and any display name
+fn config_line(part: &SplitPart, part_filename: &str) -> String {
+    let mut line = format!("{} {}", quote_token(&part.name), part_filename);
+    if let Some(entrance) = part.entrance {
+        line.push_str(&format!(" {}{}{}", ENTER_OPTION, OPTION_SEPARATOR, entrance));
+    }
+    if let Some(exit) = part.exit {
+        line.push_str(&format!(" {}{}{}", EXIT_OPTION, OPTION_SEPARATOR, exit));
+    }
+    if let Some(display) = &part.display {
+        line.push_str(&format!(" {}{}{}", NAME_OPTION, OPTION_SEPARATOR, quote_token(display)));
+    }
+    line.push('\n');
+    line
+}
+
+/// Writes the script, one config per fragment and one part file per player of each fragment
+/// into `directory`, named after `stem`; returns the path of the script file
+fn write_files(fragments: &[SplitFragment], directory: &Path, stem: &str) -> Result<PathBuf, PlayError> {
+    let mut script_text = String::new();
+
+    for (index, fragment) in fragments.iter().enumerate() {
+        let config_filename = format!("{}_config_{}.txt", stem, index + 1);
+        if !fragment.title.is_empty() {
+            script_text.push_str(&format!("{} {}\n", SCENE_MARKER, fragment.title));
+        }
+        script_text.push_str(&format!("{}\n", config_filename));
+
+        let mut config_text = String::new();
+        let mut used: HashSet<String> = HashSet::new();
+        for part in &fragment.parts {
+            // Names that only differ in punctuation or case would otherwise share a file
+            let mut part_filename = format!("{}_{}_{}.txt", stem, index + 1, file_stem(&part.name));
+            while !used.insert(part_filename.clone()) {
+                part_filename = format!("{}_{}_{}_{}.txt", stem, index + 1, file_stem(&part.name), used.len());
+            }
+            write_file(&directory.join(&part_filename), &part_text(part))?;
+            config_text.push_str(&config_line(part, &part_filename));
+        }
+        write_file(&directory.join(&config_filename), &config_text)?;
+    }
+
+    let script_path = directory.join(format!("{}_script.txt", stem));
+    write_file(&script_path, &script_text)?;
+    Ok(script_path)
+}
+
+/// Line (1-based) of the first difference between two texts
+fn first_difference(expected: &str, actual: &str) -> usize {
+    let mut expected_lines = expected.split('\n');
+    let mut actual_lines = actual.split('\n');
+    let mut line: usize = 1;
+    while expected_lines.next() == actual_lines.next() {
+        line += 1;
+    }
+    line
+}
+
+/// Splits the rendered script `rendered_filename` into script, config and part files written to
+/// `directory` (created if needed), and returns the path of the new script file:
+/// - Fails if the rendered script cannot be read back into fragments
+/// - Fails if the written files do not recite to exactly the rendered script
+pub fn split<P: AsRef<Path>, Q: AsRef<Path>>(rendered_filename: P, directory: Q) -> Result<PathBuf, PlayError> {
+    let rendered_path = rendered_filename.as_ref();
+    let directory = directory.as_ref();
+
+    let rendered = match fs::read_to_string(rendered_path) {
+        Ok(rendered) => rendered,
+        Err(source) => return Err(PlayError::FailedToOpenFile { path: rendered_path.to_path_buf(), source }),
+    };
+    let fragments = rebuild(rendered_path, &rendered)?;
+
+    if let Err(source) = fs::create_dir_all(directory) {
+        return Err(PlayError::FailedToOpenFile { path: directory.to_path_buf(), source });
+    }
+    let stem = rendered_path.file_stem().map_or("play".to_string(), |stem| file_stem(&stem.to_string_lossy()));
+    let script_path = write_files(&fragments, directory, &stem)?;
+
+    // The split is only useful if it performs the same play
+    let mut play = Play::new();
+    play.prepare(&script_path)?;
+    let mut recited: Vec<u8> = Vec::new();
+    play.recite_to(&mut recited)?;
+    let recited = String::from_utf8_lossy(&recited);
+    if recited != rendered {
+        return Err(PlayError::UnsplittableScript {
+            path: rendered_path.to_path_buf(),
+            line: first_difference(&rendered, &recited),
+            message: format!("the split files in '{}' recite differently from here on", directory.display()),
+        });
+    }
+
+    Ok(script_path)
+}
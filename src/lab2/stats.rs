@@ -0,0 +1,77 @@
+/// stats.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Summary figures for a loaded play: how many scenes, fragments and lines it has,
+/// and how the lines are shared out between the parts.
+use std::io::{self, Write};
+use super::json::quote;
+use super::play::Play;
+
+pub struct PartStats {
+    /// The name the part is first printed under
+    pub name: String,
+    /// The character the part is, which may be printed under other names elsewhere
+    pub identity: String,
+    /// Number of fragments the part appears in
+    pub fragments: usize,
+    pub lines: usize,
+}
+
+pub struct PlayStats {
+    pub scenes: usize,
+    pub fragments: usize,
+    pub lines: usize,
+    /// One entry per part, in order of first appearance
+    pub parts: Vec<PartStats>,
+}
+
+impl PlayStats {
+    pub fn new(play: &Play) -> PlayStats {
+        let mut stats = PlayStats {
+            scenes: play.scene_count(),
+            fragments: play.fragments().len(),
+            lines: 0,
+            parts: Vec::new(),
+        };
+
+        for fragment in play.fragments() {
+            for player in fragment.players() {
+                let line_count = player.lines().len();
+                stats.lines += line_count;
+                // A character is counted once, however their name is spelled or printed
+                match stats.parts.iter_mut().find(|part| part.identity == *player.identity()) {
+                    Some(part) => {
+                        part.fragments += 1;
+                        part.lines += line_count;
+                    }
+                    None => stats.parts.push(PartStats {
+                        name: player.name().clone(),
+                        identity: player.identity().clone(),
+                        fragments: 1,
+                        lines: line_count,
+                    }),
+                }
+            }
+        }
+        stats
+    }
+
+    pub fn write_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "scenes: {}", self.scenes)?;
+        writeln!(out, "fragments: {}", self.fragments)?;
+        writeln!(out, "parts: {}", self.parts.len())?;
+        writeln!(out, "lines: {}", self.lines)?;
+        for part in &self.parts {
+            writeln!(out, "    {}: {} line(s) in {} fragment(s)", part.name, part.lines, part.fragments)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_json(&self, out: &mut dyn Write) -> io::Result<()> {
+        let parts: Vec<String> = self.parts.iter()
+            .map(|part| format!("{{\"name\":{},\"fragments\":{},\"lines\":{}}}",
+                                quote(&part.name), part.fragments, part.lines))
+            .collect();
+        writeln!(out, "{{\"scenes\":{},\"fragments\":{},\"lines\":{},\"parts\":[{}]}}",
+                 self.scenes, self.fragments, self.lines, parts.join(","))
+    }
+}
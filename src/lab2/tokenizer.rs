@@ -0,0 +1,82 @@
+/// tokenizer.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Splits script and config lines into tokens. Quotes let a part name or a filename
+/// contain spaces, e.g. "First Witch" or "act one.txt", and a backslash lets a token contain a
+/// quote. Used by both Play::add_config and SceneFragment::add_config so the two read names the same way.
+pub type Tokens<'a> = Vec<(String, &'a str)>; // (text, raw_slice_of_line)
+
+// Text between a pair of these is part of one token, spaces and all
+pub const QUOTE: char = '"';
+// Makes a following quote, backslash or whitespace character part of the token; any other backslash is kept
+pub const ESCAPE: char = '\\';
+
+/// Whether the escape character makes `c` ordinary text
+fn is_escapable(c: char) -> bool {
+    c == QUOTE || c == ESCAPE || c.is_whitespace()
+}
+
+/// Splits a line into tokens at whitespace outside quotes:
+/// - Each token's text has its quotes and escapes removed; its raw slice is the token as written,
+///   so problems can be located with column_of()
+/// - Fails with the rest of the line from a quote that is never closed, for the same purpose
+pub fn tokenize(line: &str) -> Result<Tokens<'_>, &str> {
+    let mut tokens: Tokens = Vec::new();
+    let mut current: Option<(String, usize)> = None; // (text, start) of the token being read
+    let mut open_quote: Option<usize> = None;
+    let mut chars = line.char_indices().peekable();
+
+    while let Some((offset, c)) = chars.next() {
+        if c.is_whitespace() && open_quote.is_none() {
+            if let Some((text, start)) = current.take() {
+                tokens.push((text, &line[start..offset]));
+            }
+            continue;
+        }
+
+        let (text, _) = current.get_or_insert_with(|| (String::new(), offset));
+        match c {
+            ESCAPE => match chars.peek() {
+                Some(&(_, next)) if is_escapable(next) => {
+                    text.push(next);
+                    chars.next();
+                }
+                _ => text.push(c),
+            },
+            QUOTE if open_quote.is_some() => open_quote = None,
+            QUOTE => open_quote = Some(offset),
+            _ => text.push(c),
+        }
+    }
+
+    if let Some(offset) = open_quote {
+        return Err(&line[offset..]);
+    }
+    if let Some((text, start)) = current {
+        tokens.push((text, &line[start..]));
+    }
+    Ok(tokens)
+}
+
+/// `text` written as a single token: quoted, with quotes and backslashes escaped, if tokenize()
+/// would otherwise read it differently
+pub fn quote_token(text: &str) -> String {
+    // A backslash is read as written unless an escapable character, or the separator after the token, follows it
+    let mut chars = text.chars().peekable();
+    let mut plain = !text.is_empty();
+    while let Some(c) = chars.next() {
+        let escapes_next = c == ESCAPE && chars.peek().is_none_or(|&next| is_escapable(next));
+        plain &= c != QUOTE && !c.is_whitespace() && !escapes_next;
+    }
+    if plain {
+        return text.to_string();
+    }
+    let mut quoted = String::from(QUOTE);
+    for c in text.chars() {
+        if c == QUOTE || c == ESCAPE {
+            quoted.push(ESCAPE);
+        }
+        quoted.push(c);
+    }
+    quoted.push(QUOTE);
+    quoted
+}
@@ -0,0 +1,20 @@
+/// lib.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Library root. Exposes the types needed to load, validate and recite a play
+/// so that other tools can drive a performance without going through the command line.
+mod lab2;
+
+pub use lab2::bundle::{pack, unpack, Bundle};
+pub use lab2::declarations;
+pub use lab2::diagnostics::{Diagnostic, DiagnosticKind, Diagnostics, Severity};
+pub use lab2::export::write_json;
+pub use lab2::play::{Play, ScriptConfig, ScriptEntry, ScriptEntryKind, Fragments};
+pub use lab2::play_error::PlayError;
+pub use lab2::player::{canonical_name, Cue, Player, PlayLines};
+pub use lab2::return_wrapper::ReturnWrapper;
+pub use lab2::scene_fragment::{SceneFragment, PlayConfig, Cast, Cues};
+pub use lab2::script_gen::{grab_trimmed_file_lines, FileLines};
+pub use lab2::sink::Tee;
+pub use lab2::split::split;
+pub use lab2::stats::{PlayStats, PartStats};
+pub use lab2::tokenizer::{quote_token, tokenize, Tokens};
@@ -1,60 +1,134 @@
 /// main.rs
 /// Author: Zichu Pan, Edgar Palomino
 /// Summary: This is the entry point for the program. It handles command-line argument 
-/// parsing and orchestrates the overall execution flow.
+/// parsing and hands the script over to the lab2 library.
 use std::env;
-use std::sync::atomic::Ordering;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
 
-pub mod lab2;
-use lab2::declarations::{MIN_ARGS, MAX_ARGS, PROGRAM_NAME_INDEX, CONFIG_FILE_INDEX, 
-                         VERBOSE_FLAG_INDEX, BAD_COMMAND_LINE_ERROR, SUCCESS,
-                         WHINGE_MODE};
-use lab2::play::Play;
-use lab2::return_wrapper::ReturnWrapper;
+mod cli;
 
-fn usage(program_name: &String) {
-    println!("usage: {} <script_file_name> [whinge]", program_name);
+use cli::{Command, Format, Invocation, Options};
+use lab2::declarations::{PROGRAM_NAME_INDEX, BAD_COMMAND_LINE_ERROR, SUCCESS};
+use lab2::{pack, split, unpack, write_json, Diagnostics, Play, PlayError, PlayStats, ReturnWrapper};
+
+/// Opens the requested output file, or standard output when none was given
+fn open_output(options: &Options) -> Result<Box<dyn Write>, PlayError> {
+    match &options.output_filename {
+        Some(filename) => match File::create(filename) {
+            Ok(file) => Ok(Box::new(BufWriter::new(file))),
+            Err(source) => Err(PlayError::FailedToOpenFile { path: PathBuf::from(filename), source }),
+        },
+        None => Ok(Box::new(io::stdout().lock())),
+    }
 }
 
-fn parse_args(script_filename: &mut String) -> Result<(), u8> {
-    let mut args: Vec<String> = Vec::new();
-    for arg in env::args() {
-        args.push(arg);
+fn write_output(result: io::Result<()>, out: &mut dyn Write) -> Result<(), PlayError> {
+    match result.and_then(|_| out.flush()) {
+        Ok(()) => Ok(()),
+        Err(source) => Err(PlayError::FailedToWriteOutput { source }),
     }
+}
 
-    if args.len() < MIN_ARGS || args.len() > MAX_ARGS {
-        usage(&args[PROGRAM_NAME_INDEX]);
-        return Err(BAD_COMMAND_LINE_ERROR);
+fn report(diagnostics: &Diagnostics, options: &Options, out: &mut dyn Write) -> io::Result<()> {
+    match (options.diagnostic_format, options.command) {
+        (Format::Json, _) => diagnostics.report_json(out),
+        (Format::Text, Command::Check) => diagnostics.report_locations(out),
+        (Format::Text, _) => diagnostics.report(out),
     }
+}
 
-    if args.len() == MAX_ARGS && args[VERBOSE_FLAG_INDEX] != "whinge" {
-        usage(&args[PROGRAM_NAME_INDEX]);
-        return Err(BAD_COMMAND_LINE_ERROR);
+fn run(options: &Options) -> Result<(), PlayError> {
+    // These convert between files rather than load a play, and print the path of what they wrote
+    let written = match (options.command, &options.destination) {
+        (Command::Split, Some(directory)) => Some(split(&options.script_filename, directory)?),
+        (Command::Unpack, Some(directory)) => Some(unpack(&options.script_filename, directory)?),
+        (Command::Pack, Some(bundle_filename)) => {
+            pack(&options.script_filename, bundle_filename)?;
+            Some(PathBuf::from(bundle_filename))
+        }
+        _ => None,
+    };
+    if let Some(path) = written {
+        let mut out = open_output(options)?;
+        return write_output(writeln!(out, "{}", path.display()), &mut out);
     }
 
-    *script_filename = args[CONFIG_FILE_INDEX].clone();
+    let mut play = Play::new();
+    play.set_strict(options.strict);
+    play.set_concurrent(options.concurrent);
+    play.set_streaming(options.stream);
+    play.set_keep_going(options.command == Command::Check);
+    let prepared = play.prepare(&options.script_filename);
 
-    if args.len() == MAX_ARGS && args[VERBOSE_FLAG_INDEX] == "whinge" {
-        WHINGE_MODE.store(true, Ordering::SeqCst);
+    if options.command == Command::Check {
+        // Reporting the problems is the whole point of check, so they go to the output
+        let mut out = open_output(options)?;
+        write_output(report(play.diagnostics(), options, &mut out), &mut out)?;
+        return prepared;
     }
-    
-    Ok(())
-}
-    
-fn main() -> ReturnWrapper {
-    let mut script_filename = String::new();
 
-    if let Err(error_code) = parse_args(&mut script_filename) {
-        return ReturnWrapper::new(error_code);
+    // Whinge mode only decides whether the collected warnings are shown; when streaming most
+    // of them are only found during the recitation, so they are all shown after it
+    if options.whinge && !options.stream {
+        let _ = report(play.diagnostics(), options, &mut io::stderr());
     }
+    let performed = prepared.and_then(|_| perform(&mut play, options));
+    if options.whinge && options.stream {
+        let _ = report(play.diagnostics(), options, &mut io::stderr());
+    }
+    performed
+}
 
-    let mut play = Play::new();
+fn perform(play: &mut Play, options: &Options) -> Result<(), PlayError> {
+    if !options.scenes.is_empty() {
+        play.select_scenes(&options.scenes)?;
+    }
 
-    if let Err(error_code) = play.prepare(&script_filename) {
-        return ReturnWrapper::new(error_code);
+    let mut out = open_output(options)?;
+    match (options.command, options.format) {
+        (Command::Stats, Format::Text) => write_output(PlayStats::new(play).write_text(&mut out), &mut out),
+        (Command::Stats, Format::Json) => write_output(PlayStats::new(play).write_json(&mut out), &mut out),
+        (Command::Export, Format::Json) => write_output(write_json(play, &mut out), &mut out),
+        _ => play.recite_to(&mut out),
     }
+}
+    
+fn main() -> ReturnWrapper {
+    let args: Vec<String> = env::args().collect();
+    let program_name = args.get(PROGRAM_NAME_INDEX).map_or("lab2", String::as_str);
 
-    play.recite();
+    let options = match cli::parse_args(&args) {
+        Ok(Invocation::Run(options)) => options,
+        Ok(Invocation::Help) => {
+            println!("{}", cli::help(program_name));
+            return ReturnWrapper::new(SUCCESS);
+        }
+        Ok(Invocation::Version) => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            return ReturnWrapper::new(SUCCESS);
+        }
+        Err(message) => {
+            eprintln!("Error: {}", message);
+            println!("{}", cli::usage(program_name));
+            return ReturnWrapper::new(BAD_COMMAND_LINE_ERROR);
+        }
+    };
+
+    if let Err(error) = run(&options) {
+        match error {
+            // check has already listed every one of these problems on its output
+            PlayError::UnreadableFiles { .. } | PlayError::StrictModeViolations { .. }
+                if options.command == Command::Check => {}
+            _ if options.diagnostic_format == Format::Json => eprintln!("{}", error.to_json()),
+            _ => eprintln!("Error: {}", error),
+        }
+        // Keep the error stream to JSON lines only
+        let mut wrapper = ReturnWrapper::from(&error);
+        wrapper.set_quiet(options.diagnostic_format == Format::Json);
+        return wrapper;
+    }
 
     ReturnWrapper::new(SUCCESS)
 }
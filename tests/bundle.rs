@@ -0,0 +1,182 @@
+/// bundle.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Integration tests that pack fixtures into bundles, load bundles directly and unpack them again.
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use common::{fixture, fixture_dir, scratch_dir};
+use lab2::declarations::{FAILED_TO_OPEN_FILE, SCRIPT_PARSING_ERROR};
+use lab2::{pack, unpack, Bundle, DiagnosticKind, Play, PlayError, ReturnWrapper};
+
+fn recite(script: &Path) -> String {
+    let mut play = Play::new();
+    play.prepare(script).unwrap();
+    let mut out: Vec<u8> = Vec::new();
+    play.recite_to(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn exit_code(error: &PlayError) -> u8 {
+    ReturnWrapper::from(error).code()
+}
+
+/// Writes `files` of (path, contents) under `dir` and returns the path of the first
+fn write_tree(dir: &Path, files: &[(&str, &str)]) -> PathBuf {
+    for (path, contents) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+    dir.join(files[0].0)
+}
+
+#[test]
+fn packed_fixtures_recite_the_same() {
+    let fixtures = [
+        ("test_0", "partial_hamlet_act_ii_script.txt"),
+        ("test_16", "test16_nested_paths.txt"),
+        ("test_19", "test19_entrances_exits.txt"),
+        ("test_21", "test21_play.txt"),
+        ("test_22", "test22_inline.txt"),
+    ];
+    let out = scratch_dir("bundle_fixtures");
+    for (dir, script) in fixtures {
+        let bundle = out.join(format!("{}.bundle", dir));
+        pack(fixture(dir, script), &bundle).unwrap();
+        let expected = fs::read_to_string(fixture_dir(dir).join("tmp.txt")).unwrap();
+        assert_eq!(recite(&bundle), expected, "{} recited differently from its bundle", dir);
+    }
+    fs::remove_dir_all(&out).unwrap();
+}
+
+#[test]
+fn unpacking_restores_the_files() {
+    let out = scratch_dir("bundle_unpack");
+    let bundle = out.join("play.bundle");
+    pack(fixture("test_16", "test16_nested_paths.txt"), &bundle).unwrap();
+    let script = unpack(&bundle, out.join("unpacked")).unwrap();
+
+    assert_eq!(script, out.join("unpacked/test16_nested_paths.txt"));
+    // configs/../parts/ is stored as parts/, where the config still finds it
+    for file in ["test16_nested_paths.txt", "configs/test16_config.txt", "parts/test16_uma.txt", "parts/test16_vic.txt"] {
+        assert_eq!(fs::read_to_string(out.join("unpacked").join(file)).unwrap(),
+                   fs::read_to_string(fixture("test_16", file)).unwrap(), "{} changed", file);
+    }
+    assert_eq!(recite(&script), fs::read_to_string(fixture("test_16", "tmp.txt")).unwrap());
+    fs::remove_dir_all(&out).unwrap();
+}
+
+#[test]
+fn lines_starting_with_the_marker_survive_packing() {
+    let dir = scratch_dir("bundle_marker");
+    let script = write_tree(&dir, &[
+        ("script.txt", "[scene] Scene\nconfig.txt\n"),
+        ("config.txt", "Pip pip.txt\n"),
+        ("pip.txt", "0 Hello.\n%file not.txt\n%% twice\n"),
+    ]);
+    pack(&script, dir.join("play.bundle")).unwrap();
+    let bundle = Bundle::load(dir.join("play.bundle")).unwrap();
+    let unpacked = unpack(dir.join("play.bundle"), dir.join("unpacked")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(bundle.files().len(), 3);
+    assert_eq!(bundle.files()[2].1, "0 Hello.\n%file not.txt\n%% twice\n");
+    assert_eq!(unpacked, dir.join("unpacked/script.txt"));
+}
+
+#[test]
+fn warnings_are_located_inside_the_bundle() {
+    let bundle = fixture("test_23", "test23_play.bundle");
+    let mut play = Play::new();
+    play.prepare(&bundle).unwrap();
+    assert_eq!(play.script_files(), [bundle.join("test23_play.txt")]);
+    assert_eq!(play.fragments()[0].config_filename(), bundle.join("configs/test23_lamp.txt"));
+
+    let dir = scratch_dir("bundle_warnings");
+    let script = write_tree(&dir, &[
+        ("script.txt", "[scene] Scene\nconfig.txt\n"),
+        ("config.txt", "Pip parts/pip.txt\n"),
+        ("parts/pip.txt", "0 Hello.\nx Goodbye.\n"),
+    ]);
+    pack(&script, dir.join("play.bundle")).unwrap();
+    let mut play = Play::new();
+    play.prepare(dir.join("play.bundle")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let invalid = play.diagnostics().of_kind(DiagnosticKind::InvalidLineNumber).next().unwrap();
+    assert_eq!((invalid.file.as_path(), invalid.line), (dir.join("play.bundle/parts/pip.txt").as_path(), Some(2)));
+}
+
+#[test]
+fn bundled_plays_read_nothing_outside_the_bundle() {
+    let dir = scratch_dir("bundle_outside");
+    let bundle = dir.join("play.bundle");
+    fs::write(dir.join("pip.txt"), "0 Hello.\n").unwrap();
+    fs::write(&bundle, "%lab2-bundle 1\n%file script.txt\n[scene] Scene\nconfig.txt\n\
+                        %file config.txt\nPip ../pip.txt\nKit kit.txt\n").unwrap();
+
+    let mut play = Play::new();
+    play.set_keep_going(true);
+    let error = play.prepare(&bundle).err().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(matches!(error, PlayError::UnreadableFiles { .. }));
+    let unreadable: Vec<String> = play.diagnostics().of_kind(DiagnosticKind::UnreadableFile)
+        .map(|d| d.message.clone())
+        .collect();
+    assert_eq!(unreadable.len(), 2);
+    assert!(unreadable[0].ends_with(&format!("not inside bundle '{}'", bundle.display())));
+    assert!(unreadable[1].ends_with(&format!("no such file in bundle '{}'", bundle.display())));
+}
+
+#[test]
+fn files_outside_the_script_directory_cannot_be_packed() {
+    let dir = scratch_dir("bundle_unbundlable");
+    let script = write_tree(&dir, &[
+        ("acts/script.txt", "[scene] Scene\n../config.txt\n"),
+        ("config.txt", "Pip pip.txt\n"),
+        ("pip.txt", "0 Hello.\n"),
+    ]);
+    let error = pack(&script, dir.join("play.bundle")).err().unwrap();
+    let absolute = write_tree(&dir, &[("absolute.txt", &format!("[scene] Scene\n{}\n", dir.join("config.txt").display()))]);
+    let absolute_error = pack(&absolute, dir.join("absolute.bundle")).err().unwrap();
+    let written = dir.join("play.bundle").exists() || dir.join("absolute.bundle").exists();
+    fs::remove_dir_all(&dir).unwrap();
+
+    match &error {
+        PlayError::UnbundlableFile { path, directory } => {
+            assert_eq!((path, directory), (&dir.join("acts/../config.txt"), &dir.join("acts")));
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert_eq!(exit_code(&error), SCRIPT_PARSING_ERROR);
+    // An absolute config path is inside the directory, but the bundle would still read it from outside
+    assert_eq!(exit_code(&absolute_error), FAILED_TO_OPEN_FILE);
+    assert!(!written);
+}
+
+#[test]
+fn malformed_bundles_are_rejected() {
+    let dir = scratch_dir("bundle_malformed");
+    let bundles = [
+        ("%lab2-bundle 1\nstray line\n", 2, "this line is not in any %file section"),
+        ("%lab2-bundle 1\n%file a.txt\n%unknown\n", 3, "unknown bundle directive '%unknown'"),
+        ("%lab2-bundle 1\n%file ../a.txt\n", 2, "'../a.txt' is not a path inside the bundle"),
+        ("%lab2-bundle 1\n%file a.txt\n%file ./a.txt\n", 3, "'a.txt' appears more than once"),
+        ("%lab2-bundle 1\n", 1, "the bundle holds no files"),
+    ];
+    for (text, expected_line, expected_message) in bundles {
+        let path = dir.join("play.bundle");
+        fs::write(&path, text).unwrap();
+        let error = Play::new().prepare(&path).err().unwrap();
+        match &error {
+            PlayError::InvalidBundle { line, message, .. } => {
+                assert_eq!((*line, message.as_str()), (expected_line, expected_message));
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert_eq!(exit_code(&error), SCRIPT_PARSING_ERROR);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,170 @@
+/// check.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Integration tests for locating problems and for keep-going (lint) loads.
+mod common;
+
+use common::{fixture, relative_to_fixture, scratch_dir};
+use std::fs;
+use std::path::{Path, PathBuf};
+use lab2::{Diagnostic, DiagnosticKind, Play, PlayError, Severity};
+
+fn locations(dir: &str, script: &str) -> Vec<String> {
+    let mut play = Play::new();
+    play.set_keep_going(true);
+    let _ = play.prepare(fixture(dir, script));
+    let mut out: Vec<u8> = Vec::new();
+    play.diagnostics().report_locations(&mut out).unwrap();
+    relative_to_fixture(dir, &String::from_utf8(out).unwrap()).lines().map(String::from).collect()
+}
+
+#[test]
+fn script_problems_point_at_script_lines() {
+    // Warnings come in script order, so the first fragment's part file is reported before line 3
+    assert_eq!(locations("test_8", "test8_scene_no_title.txt")[1],
+               "test8_scene_no_title.txt:3: [scene] without a scene title");
+    assert_eq!(locations("test_9", "test9_extra_tokens.txt")[0],
+               "test9_extra_tokens.txt:2: Extra tokens after configuration file name: 'extra words here'");
+}
+
+#[test]
+fn config_problems_point_at_config_lines() {
+    let found = locations("test_7", "test7_config_issues.txt");
+    assert!(found[0].starts_with("test7_config.txt:2: Configuration line has too few tokens"));
+    assert!(found[1].starts_with("test7_config.txt:3: Configuration line has too many tokens"));
+}
+
+#[test]
+fn numbering_problems_point_at_part_lines() {
+    assert_eq!(locations("test_4", "test4_duplicate_lines.txt"),
+               ["test4_eve.txt:1: Missing line number 0", "test4_eve.txt:3: Duplicate line number 2"]);
+    assert_eq!(locations("test_5", "test5_missing_lines.txt")[1..4],
+               ["test5_grace.txt:2: Missing line number 2",
+                "test5_grace.txt:2: Missing line number 3",
+                "test5_grace.txt:2: Missing line number 4"]);
+    assert_eq!(locations("test_6", "test6_invalid_line_numbers.txt")[..2],
+               ["test6_henry.txt:2: 'abc' does not represent a valid line number",
+                "test6_henry.txt:4: '-5' does not represent a valid line number"]);
+}
+
+#[test]
+fn gaps_point_at_the_next_line_spoken_not_the_first_in_the_file() {
+    // Noah's file starts with line 5; line 1, which follows the gap, is on the file's second line
+    let found = locations("test_12", "test12_out_of_order.txt");
+    assert_eq!(found, ["test12_noah.txt:2: Missing line number 0"]);
+}
+
+#[test]
+fn keep_going_reports_every_unreadable_file() {
+    let mut play = Play::new();
+    play.set_keep_going(true);
+    let result = play.prepare(fixture("test_15", "test15_unreadable_files.txt"));
+
+    match result {
+        Err(PlayError::UnreadableFiles { diagnostics, .. }) => {
+            let found: Vec<(String, Option<usize>)> = diagnostics.iter()
+                .map(|d| (relative_to_fixture("test_15", &d.file.display().to_string()), d.line))
+                .collect();
+            assert_eq!(found, [("test15_config.txt".to_string(), Some(2)),
+                               ("test15_unreadable_files.txt".to_string(), Some(3))]);
+            assert!(diagnostics.iter().all(|d| d.severity == Severity::Error && d.kind == DiagnosticKind::UnreadableFile));
+        }
+        Err(other) => panic!("unexpected error: {:?}", other),
+        Ok(()) => panic!("unreadable files were not reported"),
+    }
+}
+
+#[test]
+fn without_keep_going_the_first_unreadable_file_stops_the_load() {
+    let result = Play::new().prepare(fixture("test_15", "test15_unreadable_files.txt"));
+    match result {
+        Err(PlayError::FailedToOpenFile { path, .. }) => assert!(path.ends_with("test15_missing_tara.txt")),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn unreadable_files_in_included_scripts_name_the_include_chain() {
+    let dir = scratch_dir("included_unreadable");
+    fs::create_dir_all(dir.join("acts")).unwrap();
+    fs::write(dir.join("script.txt"), "[scene] One\n[include] acts/one.txt\n").unwrap();
+    fs::write(dir.join("acts/one.txt"), "missing.txt\n[include] nope.txt\n").unwrap();
+    let script = dir.join("script.txt");
+
+    let mut play = Play::new();
+    play.set_keep_going(true);
+    let _ = play.prepare(&script);
+    let mut out: Vec<u8> = Vec::new();
+    play.diagnostics().report_locations(&mut out).unwrap();
+    let found = String::from_utf8(out).unwrap().replace(&format!("{}/", dir.display()), "");
+    let lines: Vec<&str> = found.lines().collect();
+    // The config named on line 1 is reported before the include on line 2, as they appear
+    assert!(lines[0].starts_with("acts/one.txt:1: Failed to open file"), "{}", found);
+    assert!(lines[1].starts_with("acts/one.txt:2: Failed to open file"), "{}", found);
+    assert!(lines.iter().all(|line| line.ends_with("(included from script.txt:2)")), "{}", found);
+
+    // Without keep-going the [include] fails while the script is parsed, before any config is read
+    let error = Play::new().prepare(&script).err().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(matches!(error.unwrapped(), PlayError::FailedToOpenFile { .. }));
+    assert_eq!((error.code(), error.line()), ("failed-to-open-file", None));
+    assert!(error.to_string().replace(&format!("{}/", dir.display()), "")
+                 .ends_with("(named at acts/one.txt:2, included from script.txt:2)"), "{}", error);
+}
+
+fn json(dir: &str, script: &str) -> Vec<String> {
+    let mut play = Play::new();
+    play.set_keep_going(true);
+    let _ = play.prepare(fixture(dir, script));
+    let mut out: Vec<u8> = Vec::new();
+    play.diagnostics().report_json(&mut out).unwrap();
+    relative_to_fixture(dir, &String::from_utf8(out).unwrap()).lines().map(String::from).collect()
+}
+
+#[test]
+fn json_carries_code_and_column() {
+    assert_eq!(json("test_9", "test9_extra_tokens.txt")[0],
+               "{\"severity\":\"warning\",\"code\":\"extra-script-tokens\",\"file\":\"test9_extra_tokens.txt\",\
+                \"line\":2,\"column\":18,\"message\":\"Extra tokens after configuration file name: 'extra words here'\"}");
+
+    let config = json("test_7", "test7_config_issues.txt");
+    assert!(config[0].contains("\"code\":\"too-few-config-tokens\",\"file\":\"test7_config.txt\",\"line\":2,\"column\":1,"));
+    assert!(config[1].contains("\"code\":\"too-many-config-tokens\",\"file\":\"test7_config.txt\",\"line\":3,\"column\":23,"));
+
+    let parts = json("test_6", "test6_invalid_line_numbers.txt");
+    assert!(parts[1].contains("\"code\":\"invalid-line-number\",\"file\":\"test6_henry.txt\",\"line\":4,\"column\":1,"));
+    assert!(parts[2].contains("\"code\":\"missing-line-number\",\"file\":\"test6_henry.txt\",\"line\":1,\"column\":null,"));
+}
+
+#[test]
+fn json_reports_errors_too() {
+    let found = json("test_15", "test15_unreadable_files.txt");
+    assert_eq!(found.len(), 2);
+    assert!(found[0].starts_with("{\"severity\":\"error\",\"code\":\"unreadable-file\",\"file\":\"test15_config.txt\",\"line\":2,"));
+}
+
+#[test]
+fn json_strings_are_escaped() {
+    let diagnostic = Diagnostic {
+        severity: Severity::Warning,
+        kind: DiagnosticKind::InvalidLineNumber,
+        file: PathBuf::from("dir\\part \"one\".txt"),
+        line: Some(1),
+        column: Some(1),
+        message: "tab\there".to_string(),
+    };
+    assert_eq!(diagnostic.to_json(),
+               "{\"severity\":\"warning\",\"code\":\"invalid-line-number\",\"file\":\"dir\\\\part \\\"one\\\".txt\",\
+                \"line\":1,\"column\":1,\"message\":\"tab\\there\"}");
+}
+
+#[test]
+fn fatal_errors_have_codes_and_locations() {
+    let bundle = PlayError::InvalidBundle { path: PathBuf::from("play.bundle"), line: 3, message: "bad".to_string() };
+    assert_eq!(bundle.to_json(),
+               "{\"severity\":\"error\",\"code\":\"invalid-bundle\",\"file\":\"play.bundle\",\"line\":3,\
+                \"column\":null,\"message\":\"Invalid bundle 'play.bundle' at line 3: bad\"}");
+    let cycle = PlayError::IncludeCycle { chain: vec![PathBuf::from("a.txt"), PathBuf::from("b.txt"), PathBuf::from("a.txt")] };
+    assert_eq!((cycle.code(), cycle.file()), ("include-cycle", Some(Path::new("b.txt"))));
+    let scene = PlayError::NoSuchScene { scene: 2, scene_count: 1 };
+    assert_eq!((scene.code(), scene.file(), scene.line()), ("no-such-scene", None, None));
+}
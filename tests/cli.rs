@@ -0,0 +1,206 @@
+/// cli.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Integration tests that run the lab2 binary inside the fixture directories.
+mod common;
+
+use std::env;
+use std::fs;
+use std::process::{Command, Output};
+use common::fixture_dir;
+
+fn run(dir: &str, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_lab2"))
+        .args(args)
+        .current_dir(fixture_dir(dir))
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn expected(dir: &str) -> String {
+    fs::read_to_string(fixture_dir(dir).join("tmp.txt")).unwrap()
+}
+
+#[test]
+fn paths_resolve_from_any_working_directory() {
+    let output = Command::new(env!("CARGO_BIN_EXE_lab2"))
+        .arg("test/test_16/test16_nested_paths.txt")
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), expected("test_16"));
+}
+
+#[test]
+fn split_writes_files_that_recite_the_same() {
+    let out = common::scratch_dir("cli_split");
+    let output = run("test_1", &["split", "tmp.txt", out.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    let script = out.join("tmp_script.txt");
+    assert_eq!(stdout(&output).trim_end(), script.to_str().unwrap());
+
+    let recited = run("test_1", &[script.to_str().unwrap()]);
+    assert_eq!(stdout(&recited), expected("test_1"));
+
+    let missing_directory = run("test_1", &["split", "tmp.txt"]);
+    assert_eq!(missing_directory.status.code(), Some(1));
+}
+
+#[test]
+fn pack_and_unpack_round_trip() {
+    let out = common::scratch_dir("cli_bundle");
+    let bundle = out.join("play.bundle");
+    let packed = run("test_21", &["pack", "test21_play.txt", bundle.to_str().unwrap()]);
+    assert_eq!(packed.status.code(), Some(0));
+    assert_eq!(stdout(&packed).trim_end(), bundle.to_str().unwrap());
+    assert_eq!(stdout(&run("test_21", &[bundle.to_str().unwrap()])), expected("test_21"));
+
+    let unpacked = run("test_21", &["unpack", bundle.to_str().unwrap(), out.join("files").to_str().unwrap()]);
+    assert_eq!(unpacked.status.code(), Some(0));
+    let script = out.join("files/test21_play.txt");
+    assert_eq!(stdout(&unpacked).trim_end(), script.to_str().unwrap());
+    assert_eq!(stdout(&run("test_21", &[script.to_str().unwrap()])), expected("test_21"));
+
+    assert_eq!(run("test_21", &["pack", "test21_play.txt"]).status.code(), Some(1));
+    assert_eq!(run("test_21", &["unpack", bundle.to_str().unwrap()]).status.code(), Some(1));
+    fs::remove_dir_all(&out).unwrap();
+}
+
+#[test]
+fn legacy_invocation_still_works() {
+    let output = run("test_1", &["test1_simple_valid.txt"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), expected("test_1"));
+
+    let whinge = run("test_7", &["test7_config_issues.txt", "whinge"]);
+    assert_eq!(whinge.status.code(), Some(0));
+    assert!(String::from_utf8(whinge.stderr).unwrap().contains("too few tokens"));
+
+    let strict = run("test_7", &["test7_config_issues.txt", "strict"]);
+    assert_eq!(strict.status.code(), Some(7));
+}
+
+#[test]
+fn recite_subcommand_matches_legacy_output() {
+    let output = run("test_11", &["recite", "test11_three_scenes.txt"]);
+    assert_eq!(stdout(&output), expected("test_11"));
+}
+
+#[test]
+fn streaming_and_concurrent_recitations_match() {
+    for flags in [&["--stream"][..], &["--concurrent"][..], &["--stream", "--concurrent", "--whinge"][..]] {
+        let mut args = vec!["recite"];
+        args.extend(flags);
+        args.push("test11_three_scenes.txt");
+        let output = run("test_11", &args);
+        assert_eq!(output.status.code(), Some(0));
+        assert_eq!(stdout(&output), expected("test_11"), "{:?}", flags);
+    }
+}
+
+#[test]
+fn help_and_version() {
+    let help = run("test_1", &["--help"]);
+    assert_eq!(help.status.code(), Some(0));
+    assert!(stdout(&help).contains("commands:"));
+
+    let version = run("test_1", &["-V"]);
+    assert_eq!(stdout(&version).trim(), format!("lab2 {}", env!("CARGO_PKG_VERSION")));
+}
+
+#[test]
+fn bad_command_lines_are_rejected() {
+    for args in [
+        &[][..],
+        &["test1_simple_valid.txt", "loudly"][..],
+        &["recite", "--frobnicate", "test1_simple_valid.txt"][..],
+        &["recite", "--scene", "zero", "test1_simple_valid.txt"][..],
+        &["recite", "--format", "json", "test1_simple_valid.txt"][..],
+        &["check", "--diagnostics", "xml", "test1_simple_valid.txt"][..],
+        &["stats", "a.txt", "b.txt"][..],
+        &["stats", "--stream", "test1_simple_valid.txt"][..],
+    ] {
+        let output = run("test_1", args);
+        assert_eq!(output.status.code(), Some(1), "{:?} was accepted", args);
+        assert!(stdout(&output).starts_with("usage:"));
+    }
+}
+
+#[test]
+fn scene_selection() {
+    let output = run("test_11", &["recite", "--scene", "2", "test11_three_scenes.txt"]);
+    let text = stdout(&output);
+    assert!(text.starts_with("Act Two\n\n[Enter Bob.]\n[Enter Charlie.]\n"));
+    assert!(text.ends_with("[Exit Charlie.]\n[Exit Bob.]\n"));
+    assert!(!text.contains("Alice."));
+
+    let missing = run("test_11", &["recite", "--scene", "4", "test11_three_scenes.txt"]);
+    assert_eq!(missing.status.code(), Some(1));
+}
+
+#[test]
+fn stats_in_both_formats() {
+    let text = stdout(&run("test_11", &["stats", "test11_three_scenes.txt"]));
+    assert!(text.starts_with("scenes: 3\nfragments: 3\nparts: 5\nlines: 12\n"));
+    assert!(text.contains("Bob: 4 line(s) in 2 fragment(s)"));
+
+    let json = stdout(&run("test_11", &["stats", "-f", "json", "-s", "1", "test11_three_scenes.txt"]));
+    assert!(json.starts_with("{\"scenes\":1,\"fragments\":1,\"lines\":4,"));
+}
+
+#[test]
+fn export_json() {
+    let json = stdout(&run("test_12", &["export", "--format", "json", "test12_out_of_order.txt"]));
+    assert!(json.contains("{\"number\":1,\"text\":\"First line.\"},{\"number\":2,"));
+}
+
+#[test]
+fn check_reports_without_reciting() {
+    let output = run("test_6", &["check", "test6_invalid_line_numbers.txt"]);
+    let text = stdout(&output);
+    assert!(text.contains("'abc' does not represent a valid line number"));
+    assert!(!text.contains("Henry."));
+}
+
+#[test]
+fn json_diagnostics() {
+    let check = stdout(&run("test_4", &["check", "--format", "json", "test4_duplicate_lines.txt"]));
+    assert_eq!(check.lines().count(), 2);
+    assert!(check.lines().all(|line| line.starts_with("{\"severity\":\"warning\",")));
+
+    let whinge = run("test_4", &["--whinge", "--diagnostics", "json", "test4_duplicate_lines.txt"]);
+    let stderr = String::from_utf8(whinge.stderr).unwrap();
+    assert!(stderr.contains("\"code\":\"duplicate-line-number\",\"file\":\"test4_eve.txt\",\"line\":3,"));
+}
+
+#[test]
+fn json_errors() {
+    let runs = [
+        ("test_1", &["check", "-f", "json", "nope.txt"][..], "{\"severity\":\"error\",\"code\":\"failed-to-open-file\",\"file\":\"nope.txt\","),
+        ("test_3", &["check", "-f", "json", "test3_empty_script.txt"][..], "\"code\":\"empty-script\",\"file\":\"test3_empty_script.txt\","),
+        ("test_2", &["check", "-f", "json", "test2_no_title.txt"][..], "\"code\":\"untitled-first-fragment\","),
+        ("test_1", &["--diagnostics", "json", "-s", "9", "test1_simple_valid.txt"][..], "\"code\":\"no-such-scene\",\"file\":null,"),
+    ];
+    for (dir, args, expected) in runs {
+        let output = run(dir, args);
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        // Every line is a JSON object, without the plain "Error: <code>" line
+        assert_eq!(stderr.lines().count(), 1, "{:?} wrote {}", args, stderr);
+        assert!(stderr.contains(expected), "{:?} wrote {}", args, stderr);
+        assert_ne!(output.status.code(), Some(0));
+    }
+}
+
+#[test]
+fn output_file() {
+    let path = env::temp_dir().join(format!("lab2_cli_output_{}.txt", std::process::id()));
+    let output = run("test_0", &["recite", "-o", path.to_str().unwrap(), "partial_hamlet_act_ii_script.txt"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+    assert_eq!(fs::read_to_string(&path).unwrap(), expected("test_0"));
+    fs::remove_file(path).unwrap();
+}
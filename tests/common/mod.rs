@@ -0,0 +1,34 @@
+// Each test crate uses only some of these helpers
+#![allow(dead_code)]
+
+/// common/mod.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Helpers shared by the integration tests.
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+/// Absolute path of a fixture directory such as "test_1".
+pub fn fixture_dir(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test").join(name)
+}
+
+/// Absolute path of a file inside a fixture directory.
+pub fn fixture(name: &str, file: &str) -> PathBuf {
+    fixture_dir(name).join(file)
+}
+
+/// Rewrites absolute fixture paths in `text` to be relative to the fixture directory,
+/// so reported locations can be compared against plain filenames.
+pub fn relative_to_fixture(name: &str, text: &str) -> String {
+    text.replace(&format!("{}/", fixture_dir(name).display()), "")
+}
+
+/// A fresh, empty scratch directory for one test, unique to this test process.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("lab2_{}_{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
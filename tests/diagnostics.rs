@@ -0,0 +1,358 @@
+/// diagnostics.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Integration tests for the warnings collected while loading the fixtures.
+mod common;
+
+use common::{fixture, scratch_dir};
+use std::fs;
+use std::path::{Path, PathBuf};
+use lab2::{DiagnosticKind, Play, PlayError};
+
+fn load(dir: &str, script: &str) -> Play {
+    load_path(&fixture(dir, script))
+}
+
+fn load_path(script: &Path) -> Play {
+    let mut play = Play::new();
+    play.prepare(script).unwrap();
+    play
+}
+
+#[test]
+fn clean_script_only_has_numbering_gaps() {
+    // Scene one starts at line 1 and scene two at line 10, so lines 0 and 0-9 are reported missing
+    let play = load("test_1", "test1_simple_valid.txt");
+    assert_eq!(play.diagnostics().len(), 11);
+    assert_eq!(play.diagnostics().count(DiagnosticKind::MissingLineNumber), 11);
+}
+
+#[test]
+fn duplicate_line_numbers_are_collected() {
+    let play = load("test_4", "test4_duplicate_lines.txt");
+    let duplicates: Vec<&String> = play.diagnostics()
+        .of_kind(DiagnosticKind::DuplicateLineNumber)
+        .map(|d| &d.message)
+        .collect();
+    assert_eq!(duplicates, ["Duplicate line number 2"]);
+}
+
+#[test]
+fn every_missing_line_number_is_collected() {
+    let play = load("test_5", "test5_missing_lines.txt");
+    // line 0, lines 2-4 and lines 6-9
+    assert_eq!(play.diagnostics().count(DiagnosticKind::MissingLineNumber), 8);
+}
+
+#[test]
+fn invalid_line_numbers_carry_file_and_line() {
+    let play = load("test_6", "test6_invalid_line_numbers.txt");
+    let henry = fixture("test_6", "test6_henry.txt");
+    let invalid: Vec<(&Path, Option<usize>)> = play.diagnostics()
+        .of_kind(DiagnosticKind::InvalidLineNumber)
+        .map(|d| (d.file.as_path(), d.line))
+        .collect();
+    assert_eq!(invalid, [(henry.as_path(), Some(2)), (henry.as_path(), Some(4))]);
+}
+
+#[test]
+fn stray_continuation_lines_are_collected() {
+    let play = load("test_17", "test17_multi_line.txt");
+    let stray: Vec<(Option<usize>, &String)> = play.diagnostics()
+        .of_kind(DiagnosticKind::StrayContinuationLine)
+        .map(|d| (d.line, &d.message))
+        .collect();
+    assert_eq!(stray, [(Some(1), &"Continuation line does not follow a numbered line: \
+                                   'This stray text has no line to continue.'".to_string())]);
+    assert_eq!(play.diagnostics().len(), 1);
+}
+
+#[test]
+fn empty_continuation_lines_are_dropped() {
+    let dir = scratch_dir("empty_continuation");
+    fs::write(dir.join("script.txt"), "[scene] Scene\nconfig.txt\n").unwrap();
+    fs::write(dir.join("config.txt"), "Pip pip.txt\n").unwrap();
+    fs::write(dir.join("pip.txt"), "0 hi\n|\n| more\n").unwrap();
+    let mut play = load_path(&dir.join("script.txt"));
+    let mut out: Vec<u8> = Vec::new();
+    play.recite_to(&mut out).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let empty: Vec<(Option<usize>, Option<usize>)> = play.diagnostics()
+        .of_kind(DiagnosticKind::EmptyContinuationLine)
+        .map(|d| (d.line, d.column))
+        .collect();
+    assert_eq!(empty, [(Some(2), Some(1))]);
+    assert_eq!(play.diagnostics().len(), 1);
+    // The speech goes on with the line after the empty one
+    assert!(String::from_utf8(out).unwrap().contains("Pip.\nhi\nmore\n"));
+}
+
+#[test]
+fn cue_options_are_not_extra_tokens() {
+    let play = load("test_19", "test19_entrances_exits.txt");
+    let diagnostics = play.diagnostics();
+    assert_eq!(diagnostics.count(DiagnosticKind::TooManyConfigTokens), 0);
+    let invalid: Vec<&String> = diagnostics.of_kind(DiagnosticKind::InvalidConfigOption).map(|d| &d.message).collect();
+    assert_eq!(invalid, ["'soon' is not a line number or 'auto' in option 'enter=soon'"]);
+}
+
+/// The cue problems found for Ben, whose part has lines 2 and 6, given the options on his config line
+fn cue_problems(options: &str) -> Vec<(Option<usize>, String)> {
+    let dir = scratch_dir(&format!("cues_{}", options.replace([' ', '='], "_")));
+    fs::write(dir.join("script.txt"), "[scene] Scene\nconfig.txt\n").unwrap();
+    fs::write(dir.join("config.txt"), format!("Ann a.txt\nBen b.txt {}\n", options)).unwrap();
+    fs::write(dir.join("a.txt"), "0 One.\n1 Two.\n3 Four.\n4 Five.\n5 Six.\n").unwrap();
+    fs::write(dir.join("b.txt"), "2 Three.\n6 Seven.\n").unwrap();
+    let play = load_path(&dir.join("script.txt"));
+    fs::remove_dir_all(&dir).unwrap();
+    play.diagnostics().of_kind(DiagnosticKind::InvalidConfigOption).map(|d| (d.line, d.message.clone())).collect()
+}
+
+#[test]
+fn exits_at_or_before_entrances_are_collected() {
+    let problems = cue_problems("enter=5 exit=1");
+    assert_eq!(problems[0], (Some(2), "Ben exits after line 1 but only enters before line 5".to_string()));
+    // Exiting after line 1 and entering before line 2 would both happen between the same two lines
+    assert!(cue_problems("enter=2 exit=1").contains(&(Some(2), "Ben exits after line 1 but only enters before line 2".to_string())));
+    assert!(cue_problems("enter=2 exit=6").is_empty());
+}
+
+#[test]
+fn entrances_after_the_first_line_are_collected() {
+    assert_eq!(cue_problems("enter=3"), [(Some(2), "Ben enters before line 3 but speaks line 2 before that".to_string())]);
+    assert!(cue_problems("enter=auto").is_empty());
+}
+
+#[test]
+fn exits_before_the_last_line_are_collected() {
+    assert_eq!(cue_problems("exit=5"), [(Some(2), "Ben exits after line 5 but still speaks line 6".to_string())]);
+    assert!(cue_problems("enter=1 exit=auto").is_empty());
+}
+
+#[test]
+fn comments_are_ignored_in_every_file() {
+    let play = load("test_20", "test20_comments.txt");
+    assert!(play.diagnostics().is_empty());
+}
+
+#[test]
+fn lines_after_comments_keep_their_line_numbers() {
+    let dir = scratch_dir("comment_lines");
+    fs::write(dir.join("script.txt"), "# notes\n[scene] Scene\nconfig.txt\n").unwrap();
+    fs::write(dir.join("config.txt"), "# who plays whom\nPip pip.txt\n").unwrap();
+    fs::write(dir.join("pip.txt"), "# first\n0 Hello.\n# second\nx Goodbye.\n").unwrap();
+    let play = load_path(&dir.join("script.txt"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    let invalid: Vec<Option<usize>> = play.diagnostics()
+        .of_kind(DiagnosticKind::InvalidLineNumber)
+        .map(|d| d.line)
+        .collect();
+    assert_eq!(invalid, [Some(4)]);
+    assert_eq!(play.diagnostics().len(), 1);
+}
+
+#[test]
+fn quoted_names_are_one_token() {
+    let dir = scratch_dir("quoted_names");
+    fs::write(dir.join("script.txt"), "[scene] Scene\nconfig.txt\n").unwrap();
+    fs::write(dir.join("config.txt"),
+              "\"First Witch\" pip.txt name=\"\" job=witch\n\"Second Witch\" kit.txt \"extra words\"\n").unwrap();
+    fs::write(dir.join("pip.txt"), "0 Hello.\n").unwrap();
+    fs::write(dir.join("kit.txt"), "1 Goodbye.\n").unwrap();
+    let play = load_path(&dir.join("script.txt"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    let names: Vec<&String> = play.fragments()[0].players().iter().map(|player| player.name()).collect();
+    assert_eq!(names, ["First Witch", "Second Witch"]);
+    let options: Vec<(Option<usize>, String)> = play.diagnostics()
+        .of_kind(DiagnosticKind::InvalidConfigOption)
+        .map(|d| (d.column, d.message.clone()))
+        .collect();
+    assert_eq!(options, [(Some(23), "Option 'name=' does not give a name".to_string()),
+                         (Some(31), "Unknown configuration option 'job' (expected enter, exit or name)".to_string())]);
+    let extra = play.diagnostics().of_kind(DiagnosticKind::TooManyConfigTokens).next().unwrap();
+    assert_eq!((extra.line, extra.column), (Some(2), Some(24)));
+    assert_eq!(play.diagnostics().len(), 3);
+}
+
+#[test]
+fn unterminated_quotes_are_collected() {
+    let dir = scratch_dir("unterminated_quotes");
+    fs::write(dir.join("script.txt"), "[scene] Scene\n[include] \"more scenes.txt\nconfig.txt \"x\nconfig.txt\n").unwrap();
+    fs::write(dir.join("config.txt"), "Pip pip.txt\n\"Kit kit.txt name=Kit\n").unwrap();
+    fs::write(dir.join("pip.txt"), "0 Hello.\n").unwrap();
+    let play = load_path(&dir.join("script.txt"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    // The rest of each line is skipped, so Kit is never added
+    assert_eq!(play.fragments()[0].players().len(), 1);
+    let quotes: Vec<(PathBuf, Option<usize>, Option<usize>, String)> = play.diagnostics()
+        .of_kind(DiagnosticKind::UnterminatedQuote)
+        .map(|d| (d.file.clone(), d.line, d.column, d.message.clone()))
+        .collect();
+    assert_eq!(quotes, [
+        (dir.join("script.txt"), Some(2), Some(11), "Quote is never closed: \"more scenes.txt".to_string()),
+        (dir.join("script.txt"), Some(3), Some(12), "Quote is never closed: \"x".to_string()),
+        (dir.join("config.txt"), Some(2), Some(1), "Quote is never closed: \"Kit kit.txt name=Kit".to_string()),
+    ]);
+}
+
+#[test]
+fn config_token_counts_are_collected() {
+    let play = load("test_7", "test7_config_issues.txt");
+    let diagnostics = play.diagnostics();
+    assert_eq!(diagnostics.count(DiagnosticKind::TooFewConfigTokens), 1);
+    assert_eq!(diagnostics.count(DiagnosticKind::TooManyConfigTokens), 1);
+    let too_many = diagnostics.of_kind(DiagnosticKind::TooManyConfigTokens).next().unwrap();
+    assert_eq!(too_many.line, Some(3));
+}
+
+#[test]
+fn script_problems_are_collected() {
+    let untitled = load("test_8", "test8_scene_no_title.txt");
+    assert_eq!(untitled.diagnostics().count(DiagnosticKind::SceneWithoutTitle), 1);
+
+    let extra = load("test_9", "test9_extra_tokens.txt");
+    let diagnostic = extra.diagnostics().of_kind(DiagnosticKind::ExtraScriptTokens).next().unwrap();
+    assert_eq!(diagnostic.line, Some(2));
+    assert_eq!(diagnostic.to_string(), "Warning: Extra tokens after configuration file name: 'extra words here'");
+}
+
+#[test]
+fn include_problems_name_the_including_scripts() {
+    let dir = scratch_dir("include_problems");
+    fs::write(dir.join("script.txt"), "[include] act.txt\n[include]\n").unwrap();
+    fs::write(dir.join("act.txt"), "[scene]\n[include] scene.txt extra\n").unwrap();
+    fs::write(dir.join("scene.txt"), "[scene] Scene\nconfig.txt\n").unwrap();
+    fs::write(dir.join("config.txt"), "Pip pip.txt\n").unwrap();
+    fs::write(dir.join("pip.txt"), "0 Hello.\n").unwrap();
+    let play = load_path(&dir.join("script.txt"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    let diagnostics = play.diagnostics();
+    let untitled = diagnostics.of_kind(DiagnosticKind::SceneWithoutTitle).next().unwrap();
+    assert_eq!(untitled.file, dir.join("act.txt"));
+    assert_eq!(untitled.message, format!("[scene] without a scene title (included from {}:1)",
+                                         dir.join("script.txt").display()));
+    let extra = diagnostics.of_kind(DiagnosticKind::ExtraScriptTokens).next().unwrap();
+    assert_eq!((extra.line, extra.column), (Some(2), Some(21)));
+    let unnamed = diagnostics.of_kind(DiagnosticKind::IncludeWithoutFile).next().unwrap();
+    assert_eq!((&unnamed.file, unnamed.line), (&dir.join("script.txt"), Some(2)));
+    assert_eq!(diagnostics.len(), 3);
+}
+
+#[test]
+fn config_block_problems_are_collected() {
+    let dir = scratch_dir("config_blocks");
+    let script = "[scene] Scene\n[end]\n[config] now\n  Pip pip.txt extra\n[end] done\n[config]\nPip pip.txt\n";
+    fs::write(dir.join("script.txt"), script).unwrap();
+    fs::write(dir.join("pip.txt"), "0 Hello.\n").unwrap();
+    let play = load_path(&dir.join("script.txt"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    let located = |kind: DiagnosticKind| -> Vec<(Option<usize>, Option<usize>)> {
+        play.diagnostics().of_kind(kind).map(|d| (d.line, d.column)).collect()
+    };
+    assert_eq!(located(DiagnosticKind::EndWithoutConfig), [(Some(2), Some(1))]);
+    assert_eq!(located(DiagnosticKind::ExtraScriptTokens), [(Some(3), Some(10)), (Some(5), Some(7))]);
+    // Lines inside a block are config lines, located in the script
+    assert_eq!(located(DiagnosticKind::TooManyConfigTokens), [(Some(4), Some(15))]);
+    assert_eq!(located(DiagnosticKind::ConfigWithoutEnd), [(Some(6), Some(1))]);
+    assert_eq!(play.diagnostics().len(), 5);
+    assert_eq!(play.fragments().len(), 2);
+}
+
+#[test]
+fn directives_close_an_open_config_block() {
+    let dir = scratch_dir("config_block_directives");
+    fs::write(dir.join("script.txt"), "[scene] A\n[config]\nAnn ann.txt\n[scene] B\ncal_config.txt\n").unwrap();
+    fs::write(dir.join("cal_config.txt"), "Cal cal.txt\n").unwrap();
+    fs::write(dir.join("ann.txt"), "0 Hello.\n").unwrap();
+    fs::write(dir.join("cal.txt"), "0 Goodbye.\n").unwrap();
+    let play = load_path(&dir.join("script.txt"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    // The warning points at the block's [config] line, and [scene] B still starts a scene
+    let unclosed: Vec<Option<usize>> = play.diagnostics().of_kind(DiagnosticKind::ConfigWithoutEnd)
+        .map(|d| d.line).collect();
+    assert_eq!(unclosed, [Some(2)]);
+    assert_eq!(play.diagnostics().len(), 1);
+    let fragments: Vec<(&str, Vec<&String>)> = play.fragments().iter()
+        .map(|fragment| (fragment.title(), fragment.players().iter().map(|player| player.name()).collect()))
+        .collect();
+    assert_eq!(fragments, [("A", vec![&"Ann".to_string()]), ("B", vec![&"Cal".to_string()])]);
+}
+
+#[test]
+fn plays_keep_separate_diagnostics() {
+    let noisy = load("test_5", "test5_missing_lines.txt");
+    let quiet = load("test_12", "test12_out_of_order.txt");
+    assert_eq!(noisy.diagnostics().len(), 8);
+    assert_eq!(quiet.diagnostics().len(), 1);
+}
+
+#[test]
+fn report_writes_one_warning_per_line() {
+    let play = load("test_7", "test7_config_issues.txt");
+    let mut out: Vec<u8> = Vec::new();
+    play.diagnostics().report(&mut out).unwrap();
+    let report = String::from_utf8(out).unwrap();
+    assert_eq!(report.lines().count(), play.diagnostics().len());
+    assert!(report.lines().all(|line| line.starts_with("Warning: ")));
+}
+
+/// Writes a play of `fragments` fragments with `parts` parts each, where every part file has one
+/// invalid line, and returns the script path and the part files in script and config order
+fn write_large_play(name: &str, fragments: usize, parts: usize) -> (PathBuf, Vec<PathBuf>) {
+    let dir = scratch_dir(name);
+    let mut script = String::from("[scene] A Large Scene\n");
+    let mut part_paths: Vec<PathBuf> = Vec::new();
+    for fragment in 0..fragments {
+        let mut config = String::new();
+        for part in 0..parts {
+            let part_filename = format!("part_{}_{}.txt", fragment, part);
+            config.push_str(&format!("P{} {}\n", part, part_filename));
+            fs::write(dir.join(&part_filename), format!("{} Hello.\nx Invalid.\n", part)).unwrap();
+            part_paths.push(dir.join(part_filename));
+        }
+        fs::write(dir.join(format!("config_{}.txt", fragment)), config).unwrap();
+        script.push_str(&format!("config_{}.txt\n", fragment));
+    }
+    fs::write(dir.join("script.txt"), script).unwrap();
+    (dir.join("script.txt"), part_paths)
+}
+
+#[test]
+fn warnings_from_concurrent_loading_are_in_file_order() {
+    let (script, part_paths) = write_large_play("loading_order", 8, 8);
+    for _ in 0..5 {
+        let mut play = Play::new();
+        play.prepare(&script).unwrap();
+        let invalid: Vec<&Path> = play.diagnostics()
+            .of_kind(DiagnosticKind::InvalidLineNumber)
+            .map(|d| d.file.as_path())
+            .collect();
+        assert_eq!(invalid, part_paths.iter().map(PathBuf::as_path).collect::<Vec<&Path>>());
+    }
+    fs::remove_dir_all(script.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn the_first_missing_file_in_script_order_is_the_error() {
+    let (script, part_paths) = write_large_play("first_error", 8, 8);
+    // Fragment 3 loses its part 5 and fragment 6 its part 1
+    fs::remove_file(&part_paths[3 * 8 + 5]).unwrap();
+    fs::remove_file(&part_paths[6 * 8 + 1]).unwrap();
+
+    let mut play = Play::new();
+    let error = play.prepare(&script).err().unwrap();
+    fs::remove_dir_all(script.parent().unwrap()).unwrap();
+
+    match error {
+        PlayError::FailedToOpenFile { path, .. } => assert_eq!(path, part_paths[3 * 8 + 5]),
+        other => panic!("unexpected error: {:?}", other),
+    }
+    // Only the files read before the missing one have been reported
+    assert_eq!(play.diagnostics().count(DiagnosticKind::InvalidLineNumber), 3 * 8 + 5);
+}
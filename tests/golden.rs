@@ -0,0 +1,101 @@
+/// golden.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Golden-file runner over the fixture directories under test/. Each fixture has a
+/// golden/ directory naming its script (script_name.txt) and holding the expected standard output,
+/// whinge output on standard error, and exit code of performing it. Run with BLESS=1 to write the
+/// current results as the new expected files instead of comparing against them.
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use common::relative_to_fixture;
+use lab2::declarations::SUCCESS;
+use lab2::{Play, ReturnWrapper};
+
+const GOLDEN_DIR: &str = "golden";
+const SCRIPT_NAME_FILE: &str = "script_name.txt";
+const STDOUT_FILE: &str = "stdout.txt";
+const STDERR_FILE: &str = "stderr.txt";
+const CODE_FILE: &str = "code.txt";
+const BLESS_VARIABLE: &str = "BLESS";
+
+type Outcome = (String, String, String); // (stdout, stderr, exit_code)
+
+/// Every fixture directory under test/, in name order
+fn fixture_dirs() -> Vec<PathBuf> {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test");
+    let mut dirs: Vec<PathBuf> = fs::read_dir(&root).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// Performs a fixture's script in whinge mode the way the lab2 binary does: warnings and then any
+/// error on standard error, the recitation on standard output. Paths are reported relative to the
+/// fixture directory, as they are when the binary is run from inside it.
+fn perform(name: &str, script: &Path) -> Outcome {
+    let mut play = Play::new();
+    let mut stdout: Vec<u8> = Vec::new();
+    let mut stderr: Vec<u8> = Vec::new();
+
+    let prepared = play.prepare(script);
+    play.diagnostics().report(&mut stderr).unwrap();
+    let code = match prepared.and_then(|_| play.recite_to(&mut stdout)) {
+        Ok(()) => SUCCESS,
+        Err(error) => {
+            stderr.extend(format!("Error: {}\n", error).bytes());
+            ReturnWrapper::from(&error).code()
+        }
+    };
+
+    (relative_to_fixture(name, &String::from_utf8(stdout).unwrap()),
+     relative_to_fixture(name, &String::from_utf8(stderr).unwrap()),
+     format!("{}\n", code))
+}
+
+/// Compares one expected file against the actual text, describing any mismatch
+fn compare(name: &str, path: &Path, actual: &str, failures: &mut Vec<String>) {
+    match fs::read_to_string(path) {
+        Ok(expected) if expected == actual => {}
+        Ok(expected) => {
+            let line = expected.lines().zip(actual.lines()).take_while(|(e, a)| e == a).count() + 1;
+            failures.push(format!("{}: {} differs from line {}", name, path.display(), line));
+        }
+        Err(error) => failures.push(format!("{}: cannot read {}: {}", name, path.display(), error)),
+    }
+}
+
+#[test]
+fn fixtures_match_their_golden_files() {
+    let bless = env::var_os(BLESS_VARIABLE).is_some();
+    let mut failures: Vec<String> = Vec::new();
+
+    for dir in fixture_dirs() {
+        let name = dir.file_name().unwrap().to_string_lossy().to_string();
+        let golden = dir.join(GOLDEN_DIR);
+        let script_name = match fs::read_to_string(golden.join(SCRIPT_NAME_FILE)) {
+            Ok(script_name) => script_name.trim().to_string(),
+            Err(_) => {
+                failures.push(format!("{}: no {}/{} naming the script to perform", name, GOLDEN_DIR, SCRIPT_NAME_FILE));
+                continue;
+            }
+        };
+
+        let (stdout, stderr, code) = perform(&name, &dir.join(&script_name));
+        let expected = [(STDOUT_FILE, stdout), (STDERR_FILE, stderr), (CODE_FILE, code)];
+        for (file, actual) in &expected {
+            let path = golden.join(file);
+            if bless {
+                fs::write(&path, actual).unwrap();
+            } else {
+                compare(&name, &path, actual, &mut failures);
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "golden files do not match (rerun with {}=1 to accept):\n{}",
+            BLESS_VARIABLE, failures.join("\n"));
+}
@@ -0,0 +1,474 @@
+/// play.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Integration tests that load the fixtures under test/ through the library.
+mod common;
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+use common::{fixture, fixture_dir, scratch_dir};
+use lab2::declarations::{FAILED_TO_OPEN_FILE, SCRIPT_PARSING_ERROR, CONFIG_PARSING_ERROR,
+                         FAILED_TO_WRITE_OUTPUT};
+use lab2::{canonical_name, Cue, Play, PlayError, PlayStats, ReturnWrapper, ScriptConfig, ScriptEntryKind, Tee};
+
+fn prepare(dir: &str, script: &str) -> Result<Play, PlayError> {
+    let mut play = Play::new();
+    play.prepare(fixture(dir, script))?;
+    Ok(play)
+}
+
+#[test]
+fn valid_scripts_prepare() {
+    let fixtures = [
+        ("test_0", "partial_hamlet_act_ii_script.txt"),
+        ("test_1", "test1_simple_valid.txt"),
+        ("test_4", "test4_duplicate_lines.txt"),
+        ("test_5", "test5_missing_lines.txt"),
+        ("test_6", "test6_invalid_line_numbers.txt"),
+        ("test_7", "test7_config_issues.txt"),
+        ("test_8", "test8_scene_no_title.txt"),
+        ("test_9", "test9_extra_tokens.txt"),
+        ("test_11", "test11_three_scenes.txt"),
+        ("test_12", "test12_out_of_order.txt"),
+        ("test_13", "test13_no_lines.txt"),
+    ];
+
+    for (dir, script) in fixtures {
+        assert!(prepare(dir, script).is_ok(), "{}/{} failed to prepare", dir, script);
+    }
+}
+
+fn exit_code(error: &PlayError) -> u8 {
+    ReturnWrapper::from(error).code()
+}
+
+#[test]
+fn untitled_first_fragment_is_rejected() {
+    let error = prepare("test_2", "test2_no_title.txt").err().unwrap();
+    match &error {
+        PlayError::UntitledFirstFragment { path, config } => {
+            assert!(path.ends_with("test2_no_title.txt"));
+            assert!(config.ends_with("test2_config.txt"));
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert_eq!(exit_code(&error), SCRIPT_PARSING_ERROR);
+}
+
+#[test]
+fn empty_script_is_rejected() {
+    let error = prepare("test_3", "test3_empty_script.txt").err().unwrap();
+    assert!(matches!(error, PlayError::EmptyScript { .. }));
+    assert_eq!(exit_code(&error), SCRIPT_PARSING_ERROR);
+}
+
+#[test]
+fn empty_config_is_rejected() {
+    let error = prepare("test_10", "test10_empty_config.txt").err().unwrap();
+    match &error {
+        PlayError::EmptyConfig { path } => assert!(path.ends_with("test10_config.txt")),
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert_eq!(exit_code(&error), CONFIG_PARSING_ERROR);
+}
+
+#[test]
+fn missing_script_is_rejected() {
+    let error = prepare("test_1", "no_such_script.txt").err().unwrap();
+    assert!(matches!(error, PlayError::FailedToOpenFile { .. }));
+    assert!(error.source().is_some());
+    assert!(error.to_string().contains("no_such_script.txt"));
+    assert_eq!(exit_code(&error), FAILED_TO_OPEN_FILE);
+}
+
+fn recite(dir: &str, script: &str) -> String {
+    let mut play = prepare(dir, script).unwrap();
+    let mut out: Vec<u8> = Vec::new();
+    play.recite_to(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+// Fixtures with a recorded recitation in tmp.txt
+const RECORDED: [(&str, &str); 12] = [
+    ("test_0", "partial_hamlet_act_ii_script.txt"),
+    ("test_1", "test1_simple_valid.txt"),
+    ("test_11", "test11_three_scenes.txt"),
+    ("test_16", "test16_nested_paths.txt"),
+    ("test_17", "test17_multi_line.txt"),
+    ("test_18", "test18_stage_directions.txt"),
+    ("test_19", "test19_entrances_exits.txt"),
+    ("test_20", "test20_comments.txt"),
+    ("test_21", "test21_play.txt"),
+    ("test_22", "test22_inline.txt"),
+    ("test_24", "test24_witches.txt"),
+    ("test_25", "test25_spaces.txt"),
+];
+
+#[test]
+fn recitation_matches_recorded_output() {
+    for (dir, script) in RECORDED {
+        let expected = fs::read_to_string(fixture_dir(dir).join("tmp.txt")).unwrap();
+        assert_eq!(recite(dir, script), expected, "{}/{} recited differently", dir, script);
+    }
+}
+
+#[test]
+fn concurrent_recitation_matches_recorded_output() {
+    for (dir, script) in RECORDED {
+        let mut play = prepare(dir, script).unwrap();
+        play.set_concurrent(true);
+        let mut out: Vec<u8> = Vec::new();
+        play.recite_to(&mut out).unwrap();
+        let expected = fs::read_to_string(fixture_dir(dir).join("tmp.txt")).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), expected, "{}/{} recited differently", dir, script);
+    }
+}
+
+#[test]
+fn streaming_recitation_matches_recorded_output() {
+    for (dir, script) in RECORDED {
+        let mut play = Play::new();
+        play.set_streaming(true);
+        play.prepare(fixture(dir, script)).unwrap();
+        let mut out: Vec<u8> = Vec::new();
+        play.recite_to(&mut out).unwrap();
+        let expected = fs::read_to_string(fixture_dir(dir).join("tmp.txt")).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), expected, "{}/{} recited differently", dir, script);
+    }
+}
+
+#[test]
+fn streaming_loads_each_fragment_only_when_it_is_recited() {
+    let dir = common::scratch_dir("streaming");
+    fs::write(dir.join("script.txt"), "[scene] Act One\none.txt\n[scene] Act Two\ntwo.txt\n").unwrap();
+    fs::write(dir.join("one.txt"), "Ann ann.txt\n").unwrap();
+    fs::write(dir.join("two.txt"), "Bob bob.txt\n").unwrap();
+    fs::write(dir.join("ann.txt"), "0 Ann speaks.\n").unwrap();
+
+    // Bob's part does not exist yet, which only matters once Act Two is reached
+    let mut play = Play::new();
+    play.set_streaming(true);
+    play.prepare(dir.join("script.txt")).unwrap();
+    assert!(play.fragments().iter().all(|fragment| fragment.players().is_empty()));
+
+    let mut out: Vec<u8> = Vec::new();
+    let error = play.recite_to(&mut out).err().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(matches!(error, PlayError::FailedToOpenFile { .. }));
+    assert_eq!(exit_code(&error), FAILED_TO_OPEN_FILE);
+    let output = String::from_utf8(out).unwrap();
+    assert!(output.contains("Ann speaks."));
+    assert!(!output.contains("Act Two"));
+    // Act One was dropped once it had been recited
+    assert!(play.fragments()[0].players().is_empty());
+}
+
+#[test]
+fn only_titles_followed_by_a_fragment_are_numbered() {
+    let dir = scratch_dir("scene_numbers");
+    fs::write(dir.join("script.txt"), "[scene] A\n[scene] B\nc.txt\nd.txt\n[scene] C\nc.txt\n").unwrap();
+    fs::write(dir.join("c.txt"), "Pip pip.txt\n").unwrap();
+    fs::write(dir.join("d.txt"), "Kit pip.txt\n").unwrap();
+    fs::write(dir.join("pip.txt"), "0 Hello.\n").unwrap();
+    let mut play = Play::new();
+    play.prepare(dir.join("script.txt")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let scenes: Vec<(&str, usize)> = play.fragments().iter().map(|f| (f.title(), f.scene())).collect();
+    assert_eq!(scenes, [("B", 1), ("", 1), ("C", 2)]);
+    assert_eq!(play.scene_count(), 2);
+    play.select_scenes(&[1]).unwrap();
+    assert_eq!(play.fragments().len(), 2);
+}
+
+#[test]
+fn config_and_part_paths_are_relative_to_the_file_naming_them() {
+    let play = prepare("test_16", "test16_nested_paths.txt").unwrap();
+    let fragment = &play.fragments()[0];
+    assert_eq!(fragment.config_filename(), fixture("test_16", "configs/test16_config.txt"));
+    assert_eq!(fragment.players()[0].part_filename(), fixture("test_16", "configs/../parts/test16_uma.txt"));
+}
+
+#[test]
+fn included_scripts_are_spliced_in_place() {
+    let play = prepare("test_21", "test21_play.txt").unwrap();
+    let titles: Vec<&str> = play.fragments().iter().map(|fragment| fragment.title()).collect();
+    assert_eq!(titles, ["Act One: The Letter", "Act Two: The Reply"]);
+    // Config files are resolved relative to the included script that names them
+    assert_eq!(play.fragments()[1].config_filename(), fixture("test_21", "acts/test21_reply.txt"));
+}
+
+#[test]
+fn include_cycles_are_rejected() {
+    let dir = scratch_dir("include_cycle");
+    fs::create_dir_all(dir.join("acts")).unwrap();
+    fs::write(dir.join("script.txt"), "[include] acts/one.txt\n").unwrap();
+    fs::write(dir.join("acts/one.txt"), "[include] two.txt\n").unwrap();
+    fs::write(dir.join("acts/two.txt"), "[include] ../acts/one.txt\n").unwrap();
+
+    let mut play = Play::new();
+    let error = play.prepare(dir.join("script.txt")).err().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    match &error {
+        PlayError::IncludeCycle { chain } => assert_eq!(chain, &[dir.join("script.txt"), dir.join("acts/one.txt"),
+                                                                 dir.join("acts/two.txt"), dir.join("acts/../acts/one.txt")]),
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert_eq!(exit_code(&error), SCRIPT_PARSING_ERROR);
+}
+
+#[test]
+fn inline_configs_load_like_config_files() {
+    let dir = scratch_dir("inline_config");
+    fs::write(dir.join("inline.txt"), "[scene] Scene\n[config]\nPip pip.txt exit=1\nKit kit.txt\n[end]\n").unwrap();
+    fs::write(dir.join("external.txt"), "[scene] Scene\nconfig.txt\n").unwrap();
+    fs::write(dir.join("config.txt"), "Pip pip.txt exit=1\nKit kit.txt\n").unwrap();
+    fs::write(dir.join("pip.txt"), "0 Hello.\n").unwrap();
+    fs::write(dir.join("kit.txt"), "1 Goodbye.\n").unwrap();
+
+    let mut recitations: Vec<String> = Vec::new();
+    for script in ["inline.txt", "external.txt"] {
+        let mut play = Play::new();
+        play.prepare(dir.join(script)).unwrap();
+        let parts: Vec<&Path> = play.fragments()[0].players().iter().map(|player| player.part_filename()).collect();
+        assert_eq!(parts, [dir.join("pip.txt"), dir.join("kit.txt")]);
+        let mut out: Vec<u8> = Vec::new();
+        play.recite_to(&mut out).unwrap();
+        recitations.push(String::from_utf8(out).unwrap());
+    }
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(recitations[0], recitations[1]);
+}
+
+#[test]
+fn script_entries_describe_the_script_lines() {
+    let script = fixture("test_22", "test22_inline.txt");
+    let mut config: ScriptConfig = Vec::new();
+    Play::new().read_config(&script, &mut config).unwrap();
+    let entries: Vec<(ScriptEntryKind, &str, usize)> = config.iter()
+        .map(|entry| (entry.kind(), entry.text(), entry.source_line()))
+        .collect();
+    assert_eq!(entries, [(ScriptEntryKind::SceneTitle, "The Ferry", 1), (ScriptEntryKind::Config, "", 2),
+                         (ScriptEntryKind::SceneTitle, "The Far Bank", 6),
+                         (ScriptEntryKind::Config, "test22_config.txt", 7)]);
+    assert!(config.iter().all(|entry| entry.script_path() == script));
+}
+
+#[test]
+fn empty_inline_config_is_rejected() {
+    let dir = scratch_dir("empty_inline_config");
+    fs::write(dir.join("script.txt"), "[scene] Scene\n[config]\n[end]\n").unwrap();
+    let mut play = Play::new();
+    let error = play.prepare(dir.join("script.txt")).err().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    match &error {
+        PlayError::EmptyInlineConfig { path, line } => assert_eq!((path, *line), (&dir.join("script.txt"), 2)),
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert_eq!(exit_code(&error), CONFIG_PARSING_ERROR);
+}
+
+#[test]
+fn characters_are_recognized_under_any_name() {
+    let play = prepare("test_24", "test24_witches.txt").unwrap();
+    let cast = |fragment: usize| -> Vec<(&str, &str)> {
+        play.fragments()[fragment].players().iter()
+            .map(|player| (player.identity().as_str(), player.name().as_str()))
+            .collect()
+    };
+    assert_eq!(cast(0), [("first witch", "First Witch"), ("second witch", "Second Witch"),
+                         ("banquo", "Banquo, a General")]);
+    assert_eq!(cast(1), [("first witch", "The First Witch"), ("banquo", "BANQUO")]);
+    assert_eq!(canonical_name(" FIRST_Witch "), "first witch");
+    // Each character is counted once
+    assert_eq!(PlayStats::new(&play).parts.len(), 3);
+
+    // Neither stays on stage under a new name without an exit and entrance in between
+    let recited = recite("test_24", "test24_witches.txt");
+    assert!(!recited.contains("[Exit First Witch.]"));
+    assert!(!recited.contains("[Enter The First Witch.]"));
+    assert!(recited.ends_with("[Exit BANQUO.]\n[Exit The First Witch.]\n"));
+}
+
+#[test]
+fn quoted_names_and_filenames_may_hold_spaces() {
+    let play = prepare("test_25", "test25_spaces.txt").unwrap();
+    let first = &play.fragments()[0];
+    assert_eq!(first.config_filename(), fixture("test_25", "test25 the castle.txt"));
+    let parts: Vec<(&str, &Path)> = first.players().iter()
+        .map(|player| (player.name().as_str(), player.part_filename()))
+        .collect();
+    let lady = fixture("test_25", "the parts/test25 lady.txt");
+    let macbeth = fixture("test_25", "the parts/test25 macbeth.txt");
+    assert_eq!(parts, [("Lady Macbeth", lady.as_path()), ("The \"Scottish\" King", macbeth.as_path())]);
+    assert_eq!(play.fragments()[1].config_filename(), fixture("test_25", "test25 the hall.txt"));
+    assert!(play.diagnostics().is_empty());
+}
+
+#[test]
+fn absolute_config_paths_are_honored() {
+    let script = env::temp_dir().join(format!("lab2_absolute_paths_{}.txt", process::id()));
+    let config = fixture("test_14", "test14_config.txt");
+    fs::write(&script, format!("[scene] A Clean Scene\n{}\n", config.display())).unwrap();
+
+    let mut play = Play::new();
+    let prepared = play.prepare(&script);
+    fs::remove_file(&script).unwrap();
+    prepared.unwrap();
+    assert_eq!(play.fragments()[0].config_filename(), config);
+    assert!(play.diagnostics().is_empty());
+}
+
+#[test]
+fn continuation_lines_join_the_numbered_line_above() {
+    let play = prepare("test_17", "test17_multi_line.txt").unwrap();
+    let wren = &play.fragments()[0].players()[0];
+    assert_eq!(wren.lines()[0].1, "Friends, I have a great deal to say,\nand it will not fit\nupon a single line.");
+    assert_eq!(wren.lines()[1].1, "So I shall stop here.");
+}
+
+#[test]
+fn stage_directions_are_not_attributed_to_a_speaker() {
+    let output = recite("test_18", "test18_stage_directions.txt");
+    let lines: Vec<&str> = output.lines().collect();
+    let sword = lines.iter().position(|line| *line == "[Draws sword]").unwrap();
+    assert_eq!(lines[sword - 1], "");
+    assert_eq!(lines[sword - 2], "You shall not pass.");
+    // Yorick speaks again after the direction, so he is named again
+    assert_eq!(lines[sword + 2], "Yorick.");
+    assert_eq!(lines.iter().filter(|line| **line == "Zara.").count(), 1);
+}
+
+#[test]
+fn cues_come_from_config_options() {
+    let play = prepare("test_19", "test19_entrances_exits.txt").unwrap();
+    let cues: Vec<(&String, Option<usize>, Option<usize>)> = play.fragments()[0].players().iter()
+        .map(|player| (player.name(), player.entrance_line(), player.exit_line()))
+        .collect();
+    assert_eq!(cues, [(&"Ada".to_string(), None, None),
+                      (&"Ben".to_string(), Some(2), Some(3)),
+                      (&"Cal".to_string(), None, Some(4))]);
+    assert_eq!(Cue::from_token("auto"), Some(Cue::Auto));
+    assert_eq!(Cue::from_token("7"), Some(Cue::Line(7)));
+    assert_eq!(Cue::from_token("soon"), None);
+}
+
+#[test]
+fn mid_scene_cues_are_announced_between_lines() {
+    let output = recite("test_19", "test19_entrances_exits.txt");
+    let goings = output.find("Goings").unwrap();
+    let (first, second) = output.split_at(goings);
+    assert!(first.contains("I thought I heard footsteps.\n\n[Enter Ben.]\n\nBen.\nOnly me, Ada."));
+    assert!(first.contains("And now I must be off.\n\n[Exit Ben.]\n\nCal.\n"));
+    // Ada is still on stage, but Cal left partway through the first scene and Ben enters later
+    assert!(second.starts_with("Goings\n\n[Enter Cal.]\n\nAda.\n"));
+    assert_eq!(second.matches("[Enter Ben.]").count(), 1);
+}
+
+#[test]
+fn out_of_order_lines_are_recited_in_order() {
+    let output = recite("test_12", "test12_out_of_order.txt");
+    let spoken: Vec<&str> = output.lines().filter(|line| line.ends_with("line.")).collect();
+    assert_eq!(spoken, ["First line.", "Second line.", "Third line.", "Fourth line.", "Fifth line."]);
+}
+
+#[test]
+fn shared_line_numbers_go_to_the_first_player_listed() {
+    let dir = common::scratch_dir("shared_line_numbers");
+    fs::write(dir.join("script.txt"), "[scene] Both At Once\nconfig.txt\n").unwrap();
+    fs::write(dir.join("config.txt"), "Ann ann.txt\nBob bob.txt\n").unwrap();
+    fs::write(dir.join("ann.txt"), "1 Ann again.\n0 Ann first.\n1 Ann once more.\n").unwrap();
+    fs::write(dir.join("bob.txt"), "0 Bob first.\n1 Bob again.\n").unwrap();
+
+    let mut play = Play::new();
+    play.prepare(dir.join("script.txt")).unwrap();
+    let mut out: Vec<u8> = Vec::new();
+    play.recite_to(&mut out).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let output = String::from_utf8(out).unwrap();
+    let spoken: Vec<&str> = output.lines().filter(|line| line.starts_with("Ann ") || line.starts_with("Bob ")).collect();
+    assert_eq!(spoken, ["Ann first.", "Bob first.", "Ann again.", "Ann once more.", "Bob again."]);
+}
+
+#[test]
+fn shared_line_numbers_go_to_the_player_who_spoke_first() {
+    // Bob is listed second but speaks first, so he comes first in the fragment
+    let dir = common::scratch_dir("shared_line_numbers_first_line");
+    fs::write(dir.join("script.txt"), "[scene] Both At Once\nconfig.txt\n").unwrap();
+    fs::write(dir.join("config.txt"), "Ann ann.txt\nBob bob.txt\n").unwrap();
+    fs::write(dir.join("ann.txt"), "1 Ann first.\n5 Ann last.\n").unwrap();
+    fs::write(dir.join("bob.txt"), "0 Bob first.\n5 Bob last.\n").unwrap();
+
+    let mut play = Play::new();
+    play.prepare(dir.join("script.txt")).unwrap();
+    let mut out: Vec<u8> = Vec::new();
+    play.recite_to(&mut out).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let output = String::from_utf8(out).unwrap();
+    let spoken: Vec<&str> = output.lines().filter(|line| line.starts_with("Ann ") || line.starts_with("Bob ")).collect();
+    assert_eq!(spoken, ["Bob first.", "Ann first.", "Bob last.", "Ann last."]);
+}
+
+#[test]
+fn tee_writes_to_both_sinks() {
+    let mut play = prepare("test_1", "test1_simple_valid.txt").unwrap();
+    let mut tee = Tee::new(Vec::new(), Vec::new());
+    play.recite_to(&mut tee).unwrap();
+    let (first, second) = tee.into_inner();
+    assert!(!first.is_empty());
+    assert_eq!(first, second);
+}
+
+struct BrokenSink;
+
+impl Write for BrokenSink {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::BrokenPipe, "sink closed"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn write_failure_is_reported() {
+    let mut play = prepare("test_1", "test1_simple_valid.txt").unwrap();
+    let error = play.recite_to(&mut BrokenSink).err().unwrap();
+    assert!(matches!(error, PlayError::FailedToWriteOutput { .. }));
+    assert_eq!(exit_code(&error), FAILED_TO_WRITE_OUTPUT);
+}
+
+/// Accepts a number of bytes, then fails every write
+struct FailingSink(usize);
+
+impl Write for FailingSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() > self.0 {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "sink closed"));
+        }
+        self.0 -= buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn write_failure_mid_scene_stops_every_player_thread() {
+    // The sink fails after a few lines, while player threads are still waiting for cues
+    let mut play = prepare("test_0", "partial_hamlet_act_ii_script.txt").unwrap();
+    play.set_concurrent(true);
+    let error = play.recite_to(&mut FailingSink(300)).err().unwrap();
+    assert!(matches!(error, PlayError::FailedToWriteOutput { .. }));
+}
@@ -0,0 +1,219 @@
+/// properties.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Randomized tests that generate many multi-fragment plays, write them to a scratch
+/// directory, load and recite them, and check invariants of the performance that every play
+/// should satisfy. Each play comes from a seed, which failures report so they can be replayed.
+mod common;
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use common::scratch_dir;
+use lab2::{split, Play};
+
+const PLAYS: u64 = 200;
+const NAMES: [&str; 8] = ["Ada", "Ben", "Cal", "Dee", "Eli", "Fay", "Gus", "Hal"];
+
+/// xorshift64* generator, so runs are reproducible without any dependencies
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        // The state must never be zero
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in lo..=hi
+    fn range(&mut self, lo: usize, hi: usize) -> usize {
+        lo + (self.next() % (hi - lo + 1) as u64) as usize
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.next() % 100 < percent
+    }
+}
+
+/// A generated fragment: its title (empty when it continues a scene), its cast in config
+/// order, and its lines as (line_number, speaker, text) in line number order
+struct GeneratedFragment {
+    title: String,
+    cast: Vec<&'static str>,
+    lines: Vec<(usize, &'static str, String)>,
+}
+
+fn generate(rng: &mut Rng) -> Vec<GeneratedFragment> {
+    let mut fragments: Vec<GeneratedFragment> = Vec::new();
+    let scenes = rng.range(1, 5);
+
+    for scene in 1..=scenes {
+        for fragment in 0..rng.range(1, 3) {
+            let title = if fragment == 0 { format!("Scene {}", scene) } else { String::new() };
+
+            // Often keep part of the previous cast, so players carry over between fragments
+            let mut cast: Vec<&'static str> = Vec::new();
+            if let Some(previous) = fragments.last() {
+                for name in &previous.cast {
+                    if rng.chance(50) {
+                        cast.push(name);
+                    }
+                }
+            }
+            while cast.len() < rng.range(1, 4) {
+                let name = NAMES[rng.range(0, NAMES.len() - 1)];
+                if !cast.contains(&name) {
+                    cast.push(name);
+                }
+            }
+
+            // Increasing line numbers with gaps, spoken by random members of the cast
+            let index = fragments.len();
+            let mut line_number = rng.range(0, 3);
+            let mut lines = Vec::new();
+            for _ in 0..rng.range(1, 12) {
+                let speaker = cast[rng.range(0, cast.len() - 1)];
+                lines.push((line_number, speaker, format!("Line {} of fragment {}.", line_number, index)));
+                line_number += rng.range(1, 3);
+            }
+
+            fragments.push(GeneratedFragment { title, cast, lines });
+        }
+    }
+    fragments
+}
+
+/// Writes the script, configs and parts of a generated play, with each part's lines shuffled
+fn write_play(rng: &mut Rng, fragments: &[GeneratedFragment], dir: &Path) -> PathBuf {
+    let mut script = String::new();
+    for (index, fragment) in fragments.iter().enumerate() {
+        if !fragment.title.is_empty() {
+            script.push_str(&format!("[scene] {}\n", fragment.title));
+        }
+        let config_filename = format!("config_{}.txt", index);
+        script.push_str(&format!("{}\n", config_filename));
+
+        let mut config = String::new();
+        for name in &fragment.cast {
+            let part_filename = format!("part_{}_{}.txt", index, name);
+            config.push_str(&format!("{} {}\n", name, part_filename));
+
+            let mut part: Vec<String> = fragment.lines.iter()
+                .filter(|(_, speaker, _)| speaker == name)
+                .map(|(line_number, _, text)| format!("{} {}", line_number, text))
+                .collect();
+            for i in (1..part.len()).rev() {
+                part.swap(i, rng.range(0, i));
+            }
+            let mut part_text = part.join("\n");
+            part_text.push('\n');
+            fs::write(dir.join(&part_filename), part_text).unwrap();
+        }
+        fs::write(dir.join(&config_filename), config).unwrap();
+    }
+
+    let script_path = dir.join("script.txt");
+    fs::write(&script_path, script).unwrap();
+    script_path
+}
+
+fn recite(script: &Path) -> String {
+    recite_with(script, |_| {})
+}
+
+/// Recites with the play set up by `configure` first, e.g. in concurrent or streaming mode
+fn recite_with(script: &Path, configure: impl Fn(&mut Play)) -> String {
+    let mut play = Play::new();
+    configure(&mut play);
+    play.prepare(script).unwrap();
+    let mut out: Vec<u8> = Vec::new();
+    play.recite_to(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+/// Walks the recitation, tracking who is on stage, and checks that:
+/// - every line is spoken exactly once, in order, by its own speaker
+/// - every player has entered before speaking, and the cast on stage is exactly the fragment's
+/// - nobody enters while on stage or exits while off it, and everybody has exited by the end
+/// - titles are printed for titled fragments only, in order
+fn check_recitation(seed: u64, fragments: &[GeneratedFragment], output: &str) {
+    let expected_lines: Vec<(usize, &str, &str)> = fragments.iter().enumerate()
+        .flat_map(|(index, fragment)| fragment.lines.iter().map(move |(_, speaker, text)| (index, *speaker, text.as_str())))
+        .collect();
+    let expected_titles: Vec<&str> = fragments.iter()
+        .map(|fragment| fragment.title.as_str())
+        .filter(|title| !title.is_empty())
+        .collect();
+
+    let mut on_stage: BTreeSet<&str> = BTreeSet::new();
+    let mut speaker: Option<&str> = None;
+    let mut spoken: usize = 0;
+    let mut titles: Vec<&str> = Vec::new();
+
+    for line in output.lines() {
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("[Enter ").and_then(|rest| rest.strip_suffix(".]")) {
+            assert!(on_stage.insert(name), "seed {}: {} entered while on stage", seed, name);
+        } else if let Some(name) = line.strip_prefix("[Exit ").and_then(|rest| rest.strip_suffix(".]")) {
+            assert!(on_stage.remove(name), "seed {}: {} exited while off stage", seed, name);
+        } else if let Some(name) = line.strip_suffix('.').filter(|name| NAMES.contains(name)) {
+            speaker = Some(name);
+        } else if line.starts_with("Line ") {
+            assert!(spoken < expected_lines.len(), "seed {}: extra line '{}'", seed, line);
+            let (index, expected_speaker, expected_text) = expected_lines[spoken];
+            assert_eq!(line, expected_text, "seed {}: lines out of order", seed);
+            assert_eq!(speaker, Some(expected_speaker), "seed {}: '{}' has the wrong speaker", seed, line);
+            let cast: BTreeSet<&str> = fragments[index].cast.iter().copied().collect();
+            assert_eq!(on_stage, cast, "seed {}: wrong players on stage for '{}'", seed, line);
+            spoken += 1;
+        } else {
+            titles.push(line);
+        }
+    }
+
+    assert_eq!(spoken, expected_lines.len(), "seed {}: lines were not all spoken", seed);
+    assert!(on_stage.is_empty(), "seed {}: {:?} never exited", seed, on_stage);
+    assert_eq!(titles, expected_titles, "seed {}: wrong titles", seed);
+}
+
+#[test]
+fn random_plays_recite_every_line_once_with_consistent_entrances_and_exits() {
+    for seed in 0..PLAYS {
+        let mut rng = Rng::new(seed);
+        let fragments = generate(&mut rng);
+        let dir = scratch_dir(&format!("properties_{}", seed));
+        let script = write_play(&mut rng, &fragments, &dir);
+
+        let output = recite(&script);
+        check_recitation(seed, &fragments, &output);
+        assert_eq!(recite_with(&script, |play| play.set_concurrent(true)), output,
+                   "seed {}: concurrent recitation differs", seed);
+        assert_eq!(recite_with(&script, |play| play.set_streaming(true)), output,
+                   "seed {}: streaming recitation differs", seed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[test]
+fn random_recitations_split_back_into_the_same_play() {
+    for seed in 0..PLAYS {
+        let mut rng = Rng::new(seed);
+        let fragments = generate(&mut rng);
+        let dir = scratch_dir(&format!("properties_split_{}", seed));
+        let script = write_play(&mut rng, &fragments, &dir);
+
+        let rendered = dir.join("rendered.txt");
+        fs::write(&rendered, recite(&script)).unwrap();
+        let split_script = split(&rendered, dir.join("split"))
+            .unwrap_or_else(|error| panic!("seed {}: {}", seed, error));
+        assert_eq!(recite(&split_script), fs::read_to_string(&rendered).unwrap(), "seed {}", seed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
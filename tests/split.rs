@@ -0,0 +1,107 @@
+/// split.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Integration tests that split rendered scripts back into files and recite them again.
+mod common;
+
+use std::fs;
+use common::{fixture, scratch_dir};
+use lab2::declarations::SCRIPT_PARSING_ERROR;
+use lab2::{split, Play, PlayError, ReturnWrapper};
+
+fn recite(script: &std::path::Path) -> String {
+    let mut play = Play::new();
+    play.prepare(script).unwrap();
+    let mut out: Vec<u8> = Vec::new();
+    play.recite_to(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn recorded_recitations_round_trip() {
+    for dir in ["test_0", "test_1", "test_11", "test_16", "test_17", "test_18", "test_19", "test_24"] {
+        let rendered = fixture(dir, "tmp.txt");
+        let out = scratch_dir(&format!("split_{}", dir));
+        let script = split(&rendered, &out).unwrap();
+        assert!(script.starts_with(&out));
+        assert_eq!(recite(&script), fs::read_to_string(&rendered).unwrap(), "{} recited differently", dir);
+    }
+}
+
+#[test]
+fn cues_and_untitled_fragments_are_recovered() {
+    let out = scratch_dir("split_cues");
+    let script = split(fixture("test_19", "tmp.txt"), &out).unwrap();
+    let config = fs::read_to_string(out.join("tmp_config_1.txt")).unwrap();
+    assert!(config.contains("Ben tmp_1_ben.txt enter=2 exit=3\n"));
+    assert!(config.contains("Cal tmp_1_cal.txt exit=4\n"));
+    assert_eq!(fs::read_to_string(&script).unwrap(), "[scene] Comings\ntmp_config_1.txt\n[scene] Goings\ntmp_config_2.txt\n");
+
+    // A second fragment of the same scene shows up as dialogue after two blank lines
+    let rendered = out.join("untitled.txt");
+    fs::write(&rendered, "A Scene\n\n[Enter Leo.]\n\nLeo.\nFirst.\n\n\nLeo.\nSecond.\n\n[Exit Leo.]\n").unwrap();
+    let script = split(&rendered, &out).unwrap();
+    assert_eq!(fs::read_to_string(&script).unwrap(), "[scene] A Scene\nuntitled_config_1.txt\nuntitled_config_2.txt\n");
+}
+
+#[test]
+fn blocks_of_only_exits_or_entrances_are_read_as_recitation_would_print_them() {
+    // Mid-scene exits are announced in player order, so Ben leaving before Ann ends the fragment;
+    // Ann then comes back partway through the next one
+    let out = scratch_dir("split_readings");
+    let rendered = out.join("returns.txt");
+    let text = "A Scene\n\n[Enter Cal.]\n[Enter Ann.]\n[Enter Ben.]\n\nAnn.\nOne.\n\nBen.\nTwo.\n\n[Exit Ben.]\n[Exit Ann.]\n\n\
+                Cal.\nThree.\n\n[Enter Ann.]\n\nAnn.\nFour.\n\n[Exit Ann.]\n[Exit Cal.]\n";
+    fs::write(&rendered, text).unwrap();
+    let script = split(&rendered, &out).unwrap();
+    assert_eq!(fs::read_to_string(&script).unwrap(), "[scene] A Scene\nreturns_config_1.txt\nreturns_config_2.txt\n");
+    assert_eq!(fs::read_to_string(out.join("returns_config_2.txt")).unwrap(),
+               "Cal returns_2_cal.txt\nAnn returns_2_ann.txt enter=1\n");
+    assert_eq!(recite(&script), text);
+}
+
+#[test]
+fn characters_printed_under_new_names_get_display_names() {
+    // Nobody is announced between the scenes, so both speakers of the second are carried over
+    let out = scratch_dir("split_renamed");
+    split(fixture("test_24", "tmp.txt"), &out).unwrap();
+    assert_eq!(fs::read_to_string(out.join("tmp_config_2.txt")).unwrap(),
+               "\"First Witch\" tmp_2_first_witch.txt name=\"The First Witch\"\n\
+                \"Banquo, a General\" tmp_2_banquo__a_general.txt name=BANQUO\n");
+}
+
+#[test]
+fn multi_line_text_becomes_continuation_lines() {
+    let out = scratch_dir("split_multi_line");
+    split(fixture("test_18", "tmp.txt"), &out).unwrap();
+    // Directions go to the part of whoever spoke last
+    let yorick = fs::read_to_string(out.join("tmp_1_yorick.txt")).unwrap();
+    assert!(yorick.ends_with("7 [They fight\n| and Yorick falls]\n"));
+}
+
+#[test]
+fn scripts_must_start_with_a_title() {
+    let out = scratch_dir("split_untitled");
+    let rendered = out.join("rendered.txt");
+    fs::write(&rendered, "[Enter Leo.]\n\nLeo.\nHello.\n").unwrap();
+    let error = split(&rendered, out.join("files")).err().unwrap();
+    match &error {
+        PlayError::UnsplittableScript { line, .. } => assert_eq!(*line, 1),
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert_eq!(ReturnWrapper::from(&error).code(), SCRIPT_PARSING_ERROR);
+}
+
+#[test]
+fn scripts_that_do_not_recite_the_same_are_rejected() {
+    // Leo speaks without ever entering, so reciting the split files announces him
+    let out = scratch_dir("split_mismatch");
+    let rendered = out.join("rendered.txt");
+    fs::write(&rendered, "A Scene\n\n\nLeo.\nHello.\n").unwrap();
+    match split(&rendered, out.join("files")) {
+        Err(PlayError::UnsplittableScript { line, message, .. }) => {
+            assert_eq!(line, 3);
+            assert!(message.contains("recite differently"));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
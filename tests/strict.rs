@@ -0,0 +1,72 @@
+/// strict.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Integration tests for strict mode, where any collected warning fails the load.
+mod common;
+
+use common::fixture;
+use lab2::declarations::STRICT_MODE_ERROR;
+use lab2::{DiagnosticKind, Play, PlayError, ReturnWrapper};
+
+fn prepare_strict(dir: &str, script: &str) -> Result<Play, PlayError> {
+    let mut play = Play::new();
+    play.set_strict(true);
+    play.prepare(fixture(dir, script))?;
+    Ok(play)
+}
+
+fn violations(dir: &str, script: &str) -> Vec<DiagnosticKind> {
+    match prepare_strict(dir, script) {
+        Err(PlayError::StrictModeViolations { diagnostics, .. }) => diagnostics.iter().map(|d| d.kind).collect(),
+        Err(other) => panic!("unexpected error: {:?}", other),
+        Ok(_) => panic!("{}/{} passed strict mode", dir, script),
+    }
+}
+
+#[test]
+fn clean_script_passes() {
+    let play = prepare_strict("test_14", "test14_clean_script.txt").unwrap();
+    assert!(play.diagnostics().is_empty());
+}
+
+#[test]
+fn commented_script_passes() {
+    let play = prepare_strict("test_20", "test20_comments.txt").unwrap();
+    assert!(play.diagnostics().is_empty());
+}
+
+#[test]
+fn every_problem_is_reported() {
+    assert_eq!(violations("test_7", "test7_config_issues.txt"), [
+        DiagnosticKind::TooFewConfigTokens,
+        DiagnosticKind::TooManyConfigTokens,
+        DiagnosticKind::MissingLineNumber,
+    ]);
+}
+
+#[test]
+fn each_whinge_check_is_covered() {
+    let cases = [
+        ("test_4", "test4_duplicate_lines.txt", DiagnosticKind::DuplicateLineNumber),
+        ("test_5", "test5_missing_lines.txt", DiagnosticKind::MissingLineNumber),
+        ("test_6", "test6_invalid_line_numbers.txt", DiagnosticKind::InvalidLineNumber),
+        ("test_8", "test8_scene_no_title.txt", DiagnosticKind::SceneWithoutTitle),
+        ("test_9", "test9_extra_tokens.txt", DiagnosticKind::ExtraScriptTokens),
+    ];
+
+    for (dir, script, kind) in cases {
+        assert!(violations(dir, script).contains(&kind), "{}/{} did not report {:?}", dir, script, kind);
+    }
+}
+
+#[test]
+fn violations_map_to_their_own_exit_code() {
+    let error = prepare_strict("test_5", "test5_missing_lines.txt").err().unwrap();
+    assert_eq!(ReturnWrapper::from(&error).code(), STRICT_MODE_ERROR);
+    assert_eq!(error.to_string().lines().count(), 9);
+}
+
+#[test]
+fn lenient_mode_still_loads() {
+    let mut play = Play::new();
+    assert!(play.prepare(fixture("test_5", "test5_missing_lines.txt")).is_ok());
+}
@@ -0,0 +1,50 @@
+/// tokenizer.rs
+/// Author: Zichu Pan, Edgar Palomino
+/// Summary: Tests for the tokenizer shared by the script and config parsers.
+use lab2::{quote_token, tokenize};
+
+/// The text of each token, or the unterminated rest of the line
+fn texts(line: &str) -> Result<Vec<String>, &str> {
+    tokenize(line).map(|tokens| tokens.into_iter().map(|(text, _)| text).collect())
+}
+
+#[test]
+fn whitespace_separates_tokens() {
+    assert_eq!(texts("Pip  pip.txt\texit=2").unwrap(), ["Pip", "pip.txt", "exit=2"]);
+    assert!(texts("").unwrap().is_empty());
+}
+
+#[test]
+fn quotes_keep_spaces_in_one_token() {
+    let line = r#""First Witch" "parts/first witch.txt" name="The Witch""#;
+    let tokens = tokenize(line).unwrap();
+    let text: Vec<&str> = tokens.iter().map(|(text, _)| text.as_str()).collect();
+    assert_eq!(text, ["First Witch", "parts/first witch.txt", "name=The Witch"]);
+    // The raw slices are the tokens as written, for locating them in the line
+    let raw: Vec<&str> = tokens.iter().map(|(_, raw)| *raw).collect();
+    assert_eq!(raw, [r#""First Witch""#, r#""parts/first witch.txt""#, r#"name="The Witch""#]);
+    assert_eq!(texts("\"\"").unwrap(), [""]);
+}
+
+#[test]
+fn backslashes_escape_quotes_backslashes_and_spaces() {
+    assert_eq!(texts(r#""say \"hi\"" a\ b c\\d"#).unwrap(), [r#"say "hi""#, "a b", r"c\d"]);
+    // Any other backslash is kept, so Windows paths need no escaping
+    assert_eq!(texts(r"C:\plays\act.txt trailing\").unwrap(), [r"C:\plays\act.txt", r"trailing\"]);
+}
+
+#[test]
+fn unterminated_quotes_are_reported_from_the_quote() {
+    assert_eq!(texts(r#"Pip "pip.txt exit=2"#), Err(r#""pip.txt exit=2"#));
+    assert_eq!(texts(r#""a" "b\""#), Err(r#""b\""#));
+}
+
+#[test]
+fn quoted_tokens_read_back_unchanged() {
+    for text in ["Pip", "First Witch", "", r#"The "Scottish" King"#, r"C:\plays", r"C:\plays\", r"odd\ one", "tab\there"] {
+        let line = format!("{} next", quote_token(text));
+        assert_eq!(texts(&line).unwrap(), [text, "next"], "{} was written as {}", text, quote_token(text));
+    }
+    assert_eq!(quote_token("Pip"), "Pip");
+    assert_eq!(quote_token(r"C:\plays"), r"C:\plays");
+}